@@ -0,0 +1,25 @@
+/// test exporter: cargo run --example exporter --no-default-features --features async
+/// then: curl http://127.0.0.1:9841/metrics
+
+#[cfg(feature = "async")]
+#[tokio::main]
+async fn main() {
+    use crowbar_a2s::Exporter;
+    use std::net::ToSocketAddrs;
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+
+    let client = crowbar_a2s::Builder::new()
+        .timeout(Duration::new(5, 0))
+        .build_async()
+        .unwrap();
+
+    let mut exporter = Exporter::new(client);
+    let addrs = std::env::var("CARGO_TEST_SRCDS_ADDR").unwrap();
+    for addr in addrs.split(',') {
+        exporter.server(addr.to_socket_addrs().unwrap().next().unwrap());
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:9841").await.unwrap();
+    exporter.serve(listener).await.unwrap();
+}
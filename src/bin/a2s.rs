@@ -0,0 +1,483 @@
+//! `a2s` queries Source and GoldSrc servers from the command line.
+//!
+//! Build with `cargo install crowbar-a2s --features cli`.
+
+use crowbar_a2s::errors::{Error, QueryError, Stage};
+use crowbar_a2s::types::{GoldSrcInfo, Info, Player, Rule, ServerInfo, ServerSnapshot};
+use crowbar_a2s::{A2SClient, Builder, Engine};
+use serde_json::{json, Value};
+use std::io::ErrorKind;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+const USAGE: &str = "\
+Usage: a2s <command> [options] <address>...
+
+Commands:
+  info        Server name, map, player count and version
+  players     Players currently on the server
+  rules       Server rules (cvars)
+  ping        Round-trip time, with A2A_PING or A2S_INFO
+  snapshot    Info, players and rules at once
+
+Options:
+  --timeout <seconds>   Time each query may take [default: 5]
+  --max-size <bytes>    Maximum datagram size [default: 1400]
+  --app-id <id>         Steam app id, 2400 for The Ship
+  --goldsrc             Query GoldSrc (Half-Life 1) servers
+  --json                Print the results as JSON
+  -h, --help            Print this help
+
+Addresses default to port 27015. Up to 32 servers are queried at once.
+
+Exit status:
+  0  every server answered
+  1  other error
+  2  invalid usage
+  3  a server timed out
+  4  a server sent an invalid response
+  5  network error, including unresolved addresses
+
+When several servers fail, the status is that of the first one given.";
+
+const DEFAULT_PORT: u16 = 27015;
+
+/// Servers queried at once, each from a thread of its own
+const MAX_CONCURRENT: usize = 32;
+
+const EXIT_OTHER: u8 = 1;
+const EXIT_USAGE: u8 = 2;
+const EXIT_TIMEOUT: u8 = 3;
+const EXIT_INVALID_RESPONSE: u8 = 4;
+const EXIT_NETWORK: u8 = 5;
+
+#[derive(Debug, Clone, Copy)]
+enum Command {
+    Info,
+    Players,
+    Rules,
+    Ping,
+    Snapshot,
+}
+
+impl Command {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "info" => Some(Self::Info),
+            "players" => Some(Self::Players),
+            "rules" => Some(Self::Rules),
+            "ping" => Some(Self::Ping),
+            "snapshot" => Some(Self::Snapshot),
+            _ => None,
+        }
+    }
+}
+
+struct Args {
+    command: Command,
+    builder: Builder,
+    json: bool,
+    addrs: Vec<String>,
+}
+
+enum Parsed {
+    Run(Args),
+    Help,
+}
+
+fn value<T: std::str::FromStr>(
+    args: &mut impl Iterator<Item = String>,
+    flag: &str,
+) -> Result<T, String> {
+    let value = args
+        .next()
+        .ok_or_else(|| format!("{} requires a value", flag))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value for {}: {}", flag, value))
+}
+
+fn parse(mut args: impl Iterator<Item = String>) -> Result<Parsed, String> {
+    let mut command = None;
+    let mut builder = Builder::new();
+    let mut json = false;
+    let mut addrs = Vec::new();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Parsed::Help),
+            "--json" => json = true,
+            "--goldsrc" => {
+                builder.engine(Engine::GoldSrc);
+            }
+            "--timeout" => {
+                let seconds: f64 = value(&mut args, &arg)?;
+                let timeout = Duration::try_from_secs_f64(seconds)
+                    .ok()
+                    .filter(|timeout| !timeout.is_zero())
+                    .ok_or_else(|| format!("invalid value for --timeout: {}", seconds))?;
+                builder.timeout(timeout);
+            }
+            "--max-size" => {
+                builder.max_size(value(&mut args, &arg)?);
+            }
+            "--app-id" => {
+                builder.app_id(value(&mut args, &arg)?);
+            }
+            flag if flag.starts_with('-') => return Err(format!("unknown option: {}", flag)),
+            _ if command.is_none() => {
+                command =
+                    Some(Command::parse(&arg).ok_or_else(|| format!("unknown command: {}", arg))?)
+            }
+            _ => addrs.push(with_port(arg)),
+        }
+    }
+
+    let command = command.ok_or("missing command")?;
+    if addrs.is_empty() {
+        return Err("missing address".to_string());
+    }
+
+    Ok(Parsed::Run(Args {
+        command,
+        builder,
+        json,
+        addrs,
+    }))
+}
+
+/// Appends the default port to addresses without one. IPv6 addresses need
+/// brackets and a port, as usual.
+fn with_port(addr: String) -> String {
+    if addr.contains(':') {
+        addr
+    } else {
+        format!("{}:{}", addr, DEFAULT_PORT)
+    }
+}
+
+enum Output {
+    Info(ServerInfo),
+    Players(Vec<Player>),
+    Rules(Vec<Rule>),
+    Ping(Duration),
+    Snapshot(ServerSnapshot),
+}
+
+fn query(builder: &Builder, command: Command, addr: &str) -> Result<Output, Error> {
+    // The sync client doesn't tell servers apart, so each one gets its own
+    let client: A2SClient = builder.build_sync()?;
+
+    Ok(match command {
+        // GoldSrc servers may answer with the obsolete response format
+        Command::Info => Output::Info(client.server_info(addr)?),
+        Command::Players => Output::Players(client.players(addr)?),
+        Command::Rules => Output::Rules(client.rules(addr)?),
+        Command::Ping => Output::Ping(client.latency(addr)?),
+        Command::Snapshot => Output::Snapshot(client.snapshot(addr)?),
+    })
+}
+
+/// Queries every address, `MAX_CONCURRENT` at a time, returning the results
+/// in the order the addresses were given.
+fn query_all(args: &Args) -> Vec<Result<Output, Error>> {
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<Option<Result<Output, Error>>> = args.addrs.iter().map(|_| None).collect();
+
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..MAX_CONCURRENT.min(args.addrs.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        match args.addrs.get(i) {
+                            Some(addr) => done.push((i, query(&args.builder, args.command, addr))),
+                            None => return done,
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        for worker in workers {
+            for (i, result) in worker.join().expect("query thread panicked") {
+                results[i] = Some(result);
+            }
+        }
+    });
+
+    results
+        .into_iter()
+        .map(|result| result.expect("every address is queried"))
+        .collect()
+}
+
+/// Whether a query failed for a timeout or socket error, rather than for
+/// what the server answered.
+fn unanswered(context: &QueryError) -> bool {
+    match (context.stage, &context.source) {
+        (Stage::Request | Stage::Fragment(_), Error::ErrTimeout) => true,
+        // Datagrams too short to reassemble end early too
+        (Stage::Request | Stage::Fragment(_), Error::Io(err)) => {
+            err.kind() != ErrorKind::UnexpectedEof
+        }
+        _ => false,
+    }
+}
+
+/// Kind of failure, as named in JSON output, and the matching exit status.
+fn failure(err: &Error) -> (&'static str, u8) {
+    match err {
+        Error::RetriesExhausted { source, .. } => failure(source),
+        Error::ErrTimeout => ("timeout", EXIT_TIMEOUT),
+        Error::Io(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+            ("timeout", EXIT_TIMEOUT)
+        }
+        Error::Io(_) => ("network", EXIT_NETWORK),
+        Error::Query(context) if unanswered(context) => failure(&context.source),
+        // Servers which answered with something unusable
+        Error::Query(_)
+        | Error::InvalidResponse
+        | Error::UnexpectedHeader { .. }
+        | Error::InvalidField { .. }
+        | Error::MismatchID
+        | Error::InvalidBz2Size
+        | Error::CheckSumMismatch => ("invalid_response", EXIT_INVALID_RESPONSE),
+        _ => ("other", EXIT_OTHER),
+    }
+}
+
+/// Left-aligned columns, two spaces apart.
+fn table(rows: &[Vec<String>]) -> String {
+    let mut widths = Vec::new();
+    for row in rows {
+        widths.resize(widths.len().max(row.len()), 0);
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut out = String::new();
+    for row in rows {
+        let mut line = String::new();
+        for (cell, width) in row.iter().zip(&widths) {
+            line.push_str(&format!("{:<1$}  ", cell, width));
+        }
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+fn connected(seconds: f32) -> String {
+    let seconds = seconds.max(0.0) as u64;
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+fn milliseconds(rtt: Duration) -> String {
+    format!("{:.1} ms", rtt.as_secs_f64() * 1000.0)
+}
+
+fn field(name: &str, value: String) -> Vec<String> {
+    vec![name.to_string(), value]
+}
+
+fn info_rows(info: &Info) -> Vec<Vec<String>> {
+    let extended = &info.extended_server_info;
+    let mut rows = vec![
+        field("Name", info.name.to_string()),
+        field("Map", info.map.to_string()),
+        field("Game", format!("{} ({})", info.game, info.folder)),
+        field("App ID", info.app_id.to_string()),
+        field(
+            "Players",
+            format!("{}/{} ({} bots)", info.players, info.max_players, info.bots),
+        ),
+        field("Type", format!("{:?}", info.server_type)),
+        field("OS", format!("{:?}", info.server_os)),
+        field(
+            "Password",
+            if info.visibility { "yes" } else { "no" }.to_string(),
+        ),
+        field("VAC", if info.vac { "yes" } else { "no" }.to_string()),
+        field("Version", info.version.to_string()),
+    ];
+
+    if let Some(port) = extended.port {
+        rows.push(field("Port", port.to_string()));
+    }
+    if let Some(steam_id) = extended.steam_id {
+        rows.push(field("Steam ID", steam_id.to_string()));
+    }
+    if let Some(keywords) = &extended.keywords {
+        rows.push(field("Keywords", keywords.to_string()));
+    }
+    if let Some(tv) = &info.source_tv {
+        rows.push(field("SourceTV", format!("{} (port {})", tv.name, tv.port)));
+    }
+
+    rows
+}
+
+fn goldsrc_rows(info: &GoldSrcInfo) -> Vec<Vec<String>> {
+    let mut rows = vec![
+        field("Name", info.name.to_string()),
+        field("Map", info.map.to_string()),
+        field("Game", format!("{} ({})", info.game, info.folder)),
+        field("Address", info.address.to_string()),
+        field(
+            "Players",
+            format!("{}/{} ({} bots)", info.players, info.max_players, info.bots),
+        ),
+        field("Type", format!("{:?}", info.server_type)),
+        field("OS", format!("{:?}", info.server_os)),
+        field(
+            "Password",
+            if info.visibility { "yes" } else { "no" }.to_string(),
+        ),
+        field("VAC", if info.vac { "yes" } else { "no" }.to_string()),
+    ];
+
+    if let Some(mod_info) = &info.mod_info {
+        rows.push(field("Mod", mod_info.link.to_string()));
+        rows.push(field("Mod version", mod_info.version.to_string()));
+    }
+
+    rows
+}
+
+fn players_table(players: &[Player]) -> String {
+    if players.is_empty() {
+        return "No players\n".to_string();
+    }
+
+    let mut rows = vec![vec!["NAME".into(), "SCORE".into(), "TIME".into()]];
+    rows.extend(players.iter().map(|player| {
+        vec![
+            player.name.to_string(),
+            player.score.to_string(),
+            connected(player.duration),
+        ]
+    }));
+    table(&rows)
+}
+
+fn rules_table(rules: &[Rule]) -> String {
+    if rules.is_empty() {
+        return "No rules\n".to_string();
+    }
+
+    let mut rows = vec![vec!["NAME".into(), "VALUE".into()]];
+    rows.extend(
+        rules
+            .iter()
+            .map(|rule| vec![rule.name.to_string(), rule.value.to_string()]),
+    );
+    table(&rows)
+}
+
+fn text(output: &Output) -> String {
+    match output {
+        Output::Info(ServerInfo::Source(info)) => table(&info_rows(info)),
+        Output::Info(ServerInfo::GoldSrc(info)) => table(&goldsrc_rows(info)),
+        Output::Players(players) => players_table(players),
+        Output::Rules(rules) => rules_table(rules),
+        Output::Ping(rtt) => format!("{}\n", milliseconds(*rtt)),
+        Output::Snapshot(snapshot) => {
+            let players = match (&snapshot.players, &snapshot.players_error) {
+                (Some(players), _) => players_table(players),
+                (None, Some(err)) => format!("Players unavailable: {}\n", err),
+                (None, None) => "Players unavailable\n".to_string(),
+            };
+            let rules = match (&snapshot.rules, &snapshot.rules_error) {
+                (Some(rules), _) => rules_table(rules),
+                (None, Some(err)) => format!("Rules unavailable: {}\n", err),
+                (None, None) => "Rules unavailable\n".to_string(),
+            };
+            let mut info = info_rows(&snapshot.info);
+            info.push(field("RTT", milliseconds(snapshot.rtt)));
+            format!("{}\n{}\n{}", table(&info), players, rules)
+        }
+    }
+}
+
+fn to_json(addr: &str, result: &Result<Output, Error>) -> serde_json::Result<Value> {
+    let mut object = json!({ "address": addr });
+    let (key, value) = match result {
+        Ok(Output::Info(ServerInfo::Source(info))) => ("info", serde_json::to_value(info)?),
+        Ok(Output::Info(ServerInfo::GoldSrc(info))) => ("info", serde_json::to_value(info)?),
+        Ok(Output::Players(players)) => ("players", serde_json::to_value(players)?),
+        Ok(Output::Rules(rules)) => ("rules", serde_json::to_value(rules)?),
+        Ok(Output::Ping(rtt)) => ("rtt_ms", json!(rtt.as_secs_f64() * 1000.0)),
+        Ok(Output::Snapshot(snapshot)) => ("snapshot", serde_json::to_value(snapshot)?),
+        Err(err) => {
+            let mut error = json!({ "kind": failure(err).0, "message": err.to_string() });
+            if let Error::Query(context) = err {
+                error["stage"] = json!(context.stage.to_string());
+            }
+            ("error", error)
+        }
+    };
+    object[key] = value;
+    Ok(object)
+}
+
+fn main() -> ExitCode {
+    let args = match parse(std::env::args().skip(1)) {
+        Ok(Parsed::Run(args)) => args,
+        Ok(Parsed::Help) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprintln!("a2s: {}\n\n{}", err, USAGE);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+
+    let results = query_all(&args);
+
+    let status = results
+        .iter()
+        .find_map(|result| result.as_ref().err())
+        .map_or(0, |err| failure(err).1);
+
+    if args.json {
+        let values: serde_json::Result<Vec<Value>> = args
+            .addrs
+            .iter()
+            .zip(&results)
+            .map(|(addr, result)| to_json(addr, result))
+            .collect();
+        match values.and_then(|values| serde_json::to_string_pretty(&values)) {
+            Ok(json) => println!("{}", json),
+            Err(err) => {
+                eprintln!("a2s: {}", err);
+                return ExitCode::from(EXIT_OTHER);
+            }
+        }
+    } else {
+        let many = args.addrs.len() > 1;
+        for (i, (addr, result)) in args.addrs.iter().zip(&results).enumerate() {
+            match result {
+                Ok(output) if many => {
+                    if i > 0 {
+                        println!();
+                    }
+                    print!("== {} ==\n{}", addr, text(output));
+                }
+                Ok(output) => print!("{}", text(output)),
+                Err(err) => eprintln!("a2s: {}: {}", addr, err),
+            }
+        }
+    }
+
+    ExitCode::from(status)
+}
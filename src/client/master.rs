@@ -0,0 +1,82 @@
+use crate::client::A2SClient;
+use crate::errors::{Error, Result};
+use crate::master::{Filter, MasterQuery, Region};
+use std::net::{SocketAddr, SocketAddrV4, ToSocketAddrs};
+use std::time::Instant;
+
+/// Iterator over the servers listed by a master server, see
+/// [`A2SClient::master_servers`].
+///
+/// Pages are requested lazily as the iterator is advanced. After an error
+/// the iterator is fused.
+pub struct MasterServers<'a> {
+    client: &'a A2SClient,
+    master: SocketAddr,
+    query: MasterQuery,
+    failed: bool,
+}
+
+impl MasterServers<'_> {
+    fn request_page(&mut self) -> Result<()> {
+        let request = match self.query.next_request() {
+            Some(request) => request,
+            None => return Ok(()),
+        };
+        self.client.socket.send_to(&request, self.master)?;
+        let deadline = Instant::now() + self.client.timeout;
+
+        let mut data = vec![0; self.client.max_size];
+        loop {
+            let (read, from) = self.client.recv_from(&mut data, deadline)?;
+            if from == self.master {
+                return self.query.push_response(&data[..read]);
+            }
+        }
+    }
+}
+
+impl Iterator for MasterServers<'_> {
+    type Item = Result<SocketAddrV4>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        loop {
+            if let Some(addr) = self.query.pop() {
+                return Some(Ok(addr));
+            }
+            if self.query.is_done() {
+                return None;
+            }
+            if let Err(err) = self.request_page() {
+                self.failed = true;
+                return Some(Err(err));
+            }
+        }
+    }
+}
+
+impl A2SClient {
+    /// Lists the servers known to a master server, such as
+    /// [`MASTER_SOURCE`](crate::master::MASTER_SOURCE), matching `filter`.
+    pub fn master_servers<A: ToSocketAddrs>(
+        &self,
+        master: A,
+        region: Region,
+        filter: &Filter,
+    ) -> Result<MasterServers<'_>> {
+        let master = master
+            .to_socket_addrs()?
+            .next()
+            .ok_or(Error::Other("Master server address did not resolve"))?;
+
+        Ok(MasterServers {
+            client: self,
+            master,
+            query: MasterQuery::new(region, filter),
+            failed: false,
+        })
+    }
+}
@@ -1,79 +1,33 @@
-use crate::constants::*;
-use crate::errors::{Error, Result};
+mod master;
+#[cfg(feature = "rcon")]
+mod rcon;
+mod snapshot;
 
-use crate::types::{Info, INFO_REQUEST};
-
-use crate::types::{Player, PLAYER_REQUEST};
-
-use crate::CRC32;
-use crate::types::{Rule, RULES_REQUEST};
-use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use bzip2::read::BzDecoder;
-use std::io::{Cursor, Read, Write};
-use std::net::{ToSocketAddrs, UdpSocket};
-use std::ops::Deref;
-
-macro_rules! read_buffer_offset {
-    ($buf:expr, $offset:expr, i8) => {
-        $buf[$offset].into()
-    };
-    ($buf:expr, $offset:expr, u8) => {
-        $buf[$offset].into()
-    };
-    ($buf:expr, $offset:expr, i16) => {
-        i16::from_le_bytes([$buf[$offset], $buf[$offset + 1]])
-    };
-    ($buf:expr, $offset:expr, u16) => {
-        u16::from_le_bytes([$buf[$offset], $buf[$offset + 1]])
-    };
-    ($buf:expr, $offset:expr, i32) => {
-        i32::from_le_bytes([
-            $buf[$offset],
-            $buf[$offset + 1],
-            $buf[$offset + 2],
-            $buf[$offset + 3],
-        ])
-    };
-    ($buf:expr, $offset:expr, u32) => {
-        u32::from_le_bytes([
-            $buf[$offset],
-            $buf[$offset + 1],
-            $buf[$offset + 2],
-            $buf[$offset + 3],
-        ])
-    };
-    ($buf:expr, $offset:expr, i64) => {
-        i64::from_le_bytes([
-            $buf[$offset],
-            $buf[$offset + 1],
-            $buf[$offset + 2],
-            $buf[$offset + 3],
-            $buf[$offset + 4],
-            $buf[$offset + 5],
-            $buf[$offset + 6],
-            $buf[$offset + 7],
-        ])
-    };
-    ($buf:expr, $offset:expr, u64) => {
-        u64::from_le_bytes([
-            $buf[$offset],
-            $buf[$offset + 1],
-            $buf[$offset + 2],
-            $buf[$offset + 3],
-            $buf[$offset + 4],
-            $buf[$offset + 5],
-            $buf[$offset + 6],
-            $buf[$offset + 7],
-        ])
-    };
-}
+pub use crate::client::master::MasterServers;
+
+use crate::errors::{Error, Result};
+use crate::packet::Engine;
+#[cfg(feature = "rcon")]
+use crate::packet::Reassembler;
+use crate::query::{ChallengeCache, ChallengeMode, Query, QueryKind, QueryStatus};
+use crate::retry::{self, Backoff, RetryPolicy};
+
+use crate::types::{
+    read_header, Counted, Info, Parsed, Player, RawRule, Rule, ServerInfo, Timed, PING_RESPONSE,
+};
+use std::io::{self, Cursor, ErrorKind};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
 
 /// A2SClient is a synchronous client for the A2S protocol.
-/// It is used to query Source but not GoldSrc servers.
+/// It is used to query Source servers, and GoldSrc servers when built with
+/// [`Engine::GoldSrc`](crate::Engine::GoldSrc).
 ///
 /// # Example
 ///
-/// ```rust
+/// ```rust,no_run
+/// use std::time::Duration;
 /// use crowbar_a2s::{Builder, A2SClient};
 ///
 /// let client: A2SClient = crowbar_a2s::Builder::new()
@@ -93,6 +47,13 @@ pub struct A2SClient {
     pub(crate) max_size: usize,
     /// steam app id, if you want to query _The Ship_ servers' players, you need to set this to 2400
     pub(crate) app_id: u16,
+    pub(crate) engine: Engine,
+    pub(crate) retry: RetryPolicy,
+    pub(crate) timeout: Duration,
+    pub(crate) fragment_timeout: Option<Duration>,
+    pub(crate) challenges: Option<ChallengeCache>,
+    pub(crate) challenge_mode: ChallengeMode,
+    pub(crate) capture: bool,
 }
 
 impl A2SClient {
@@ -106,169 +67,306 @@ impl A2SClient {
         self
     }
 
-    fn send<A: ToSocketAddrs>(&self, payload: &[u8], addr: A) -> Result<Vec<u8>> {
-        self.socket.send_to(payload, addr)?;
-
-        let mut data = vec![0; self.max_size];
-
-        let read = self.socket.recv(&mut data)?;
-        data.truncate(read);
-
-        let header = read_buffer_offset!(&data, OFS_HEADER, i32);
+    pub fn engine(&mut self, engine: Engine) -> &mut Self {
+        self.engine = engine;
+        self
+    }
 
-        if header == SINGLE_PACKET {
-            Ok(data[OFS_SP_PAYLOAD..].to_vec())
-        } else if header == MULTI_PACKET {
-            // ID - long (4 bytes)
-            // Total - byte (1 byte)
-            // Number - byte (1 byte)
-            // Size - short (2 bytes)
+    /// Receives one datagram, waiting until `deadline` or for the fragment
+    /// timeout, whichever is shorter.
+    fn recv(&self, data: &mut [u8], deadline: Instant) -> Result<usize> {
+        let deadline = match self.fragment_timeout {
+            Some(fragment_timeout) => deadline.min(Instant::now() + fragment_timeout),
+            None => deadline,
+        };
+        self.recv_from(data, deadline).map(|(read, _)| read)
+    }
 
-            let id = read_buffer_offset!(&data, OFS_MP_ID, i32);
-            let total_packets: usize = data[OFS_MP_SS_TOTAL].into();
-            let switching_size: usize = read_buffer_offset!(&data, OFS_MP_SS_SIZE, u16).into();
+    /// Receives one datagram and its sender, waiting until `deadline`.
+    ///
+    /// The socket is shared by every query, so its read timeout is set for
+    /// each read rather than left as the last query set it.
+    pub(crate) fn recv_from(
+        &self,
+        data: &mut [u8],
+        deadline: Instant,
+    ) -> Result<(usize, SocketAddr)> {
+        let wait = deadline.saturating_duration_since(Instant::now());
+        if wait.is_zero() {
+            return Err(io::Error::from(ErrorKind::TimedOut).into());
+        }
 
-            // Sanity check
-            if (switching_size > self.max_size) || (total_packets > 32) {
-                return Err(Error::InvalidResponse);
-            }
+        self.socket.set_read_timeout(Some(wait))?;
+        Ok(self.socket.recv_from(data)?)
+    }
 
-            let mut packets: Vec<PacketFragment> = Vec::with_capacity(0);
-            packets.try_reserve(total_packets)?;
-            packets.push(PacketFragment {
-                number: data[OFS_MP_SS_NUMBER],
-                // The first packet seems to include a single packet header (0xFFFFFFFF) for some
-                // reason, so we'd rather skip that (hence +4)
-                payload: Vec::from(&data[OFS_MP_SS_PAYLOAD + 4..]),
-            });
-
-            loop {
-                let mut data: Vec<u8> = Vec::with_capacity(0);
-                data.try_reserve(switching_size)?;
-                data.resize(switching_size, 0);
-
-                let read = self.socket.recv(&mut data)?;
-                data.truncate(read);
-
-                if data.len() <= 9 {
-                    Err(Error::InvalidResponse)?
-                }
+    /// Sends a request, reassembling the response as split by `engine`.
+    #[cfg(feature = "rcon")]
+    fn send_as<A: ToSocketAddrs>(
+        &self,
+        payload: &[u8],
+        addr: A,
+        engine: Engine,
+    ) -> Result<Vec<u8>> {
+        self.send_gather(payload, addr, engine, None)
+            .map(|mut responses| responses.remove(0))
+    }
 
-                let packet_id = read_buffer_offset!(&data, OFS_MP_ID, i32);
+    /// Sends a request, reassembling the responses as split by `engine`.
+    /// With a `gap`, responses keep being gathered until none arrives within
+    /// `gap` of the last one.
+    #[cfg(feature = "rcon")]
+    fn send_gather<A: ToSocketAddrs>(
+        &self,
+        payload: &[u8],
+        addr: A,
+        engine: Engine,
+        gap: Option<Duration>,
+    ) -> Result<Vec<Vec<u8>>> {
+        self.socket.send_to(payload, addr)?;
+        let mut deadline = Instant::now() + self.timeout;
 
-                if packet_id != id {
-                    return Err(Error::MismatchID);
-                }
+        let mut responses = Vec::new();
+        let mut reassembler = Reassembler::with_engine(self.max_size, engine);
+        let mut data = vec![0; self.max_size];
 
-                if id as u32 & 0x80000000 == 0 {
-                    // Uncompressed packet
-                    packets.push(PacketFragment {
-                        number: data[OFS_MP_SS_NUMBER],
-                        payload: Vec::from(&data[OFS_MP_SS_PAYLOAD..]),
-                    });
-                } else {
-                    // BZip2 compressed packet
-                    packets.push(PacketFragment {
-                        number: data[OFS_MP_SS_NUMBER],
-                        payload: Vec::from(&data[OFS_MP_SS_PAYLOAD_BZ2..]),
-                    });
+        loop {
+            data.resize(reassembler.recv_size(), 0);
+            let read = match self.recv(&mut data, deadline) {
+                Ok(read) => read,
+                Err(err) if !responses.is_empty() && retry::is_retriable(&err) => {
+                    return Ok(responses)
                 }
-
-                if packets.len() == total_packets {
-                    break;
+                Err(err) => return Err(err),
+            };
+
+            if let Some(payload) = reassembler.push(&data[..read])? {
+                responses.push(payload);
+                match gap {
+                    Some(gap) => deadline = Instant::now() + gap,
+                    None => return Ok(responses),
                 }
             }
+        }
+    }
 
-            packets.sort_by_key(|p| p.number);
+    fn resolve<A: ToSocketAddrs>(addr: A) -> Result<SocketAddr> {
+        addr.to_socket_addrs()?
+            .next()
+            .ok_or(Error::Other("Server address did not resolve"))
+    }
 
-            let mut aggregation = Vec::with_capacity(0);
-            aggregation.try_reserve(total_packets * self.max_size)?;
+    /// Starts a query to `addr` as configured.
+    fn query(&self, kind: QueryKind, addr: SocketAddr) -> Query {
+        let mut query = Query::new(kind, self.max_size, self.engine);
+        query
+            .challenge_mode(self.challenge_mode)
+            .capture(self.capture);
+        if let Some(challenges) = &self.challenges {
+            challenges.prepare(addr, &mut query);
+        }
+        query
+    }
 
-            for p in packets {
-                aggregation.extend(p.payload);
-            }
+    /// Queries `addr`, parsing the response payload with `parse`.
+    fn do_challenge_request<A, T>(
+        &self,
+        addr: A,
+        kind: QueryKind,
+        parse: impl FnOnce(Vec<u8>) -> Result<T>,
+    ) -> Result<Timed<T>>
+    where
+        A: ToSocketAddrs,
+    {
+        let addr = Self::resolve(addr)?;
+        let mut query = self.query(kind, addr);
+        let data = self.execute(addr, &mut query)?;
+        Ok(Timed {
+            value: parse(data.value).map_err(|err| query.error(addr, err))?,
+            rtt: data.rtt,
+        })
+    }
 
-            if id as u32 & 0x80000000 != 0 {
-                let decompressed_size = read_buffer_offset!(&data, OFS_MP_SS_BZ2_SIZE, u32);
-                let checksum = read_buffer_offset!(&data, OFS_MP_SS_BZ2_CRC, u32);
+    /// Runs `query` to completion, retrying as configured.
+    fn execute(&self, addr: SocketAddr, query: &mut Query) -> Result<Timed<Vec<u8>>> {
+        let mut backoff = Backoff::new(self.retry);
 
-                if decompressed_size > (1024 * 1024) {
-                    return Err(Error::InvalidBz2Size);
+        loop {
+            match self.attempt(addr, query, backoff.timeout(self.timeout)) {
+                Err(err) if retry::is_retriable(&err) && query.fallback() => {}
+                Err(err) => {
+                    thread::sleep(backoff.retry(err)?);
+                    query.retry();
                 }
+                result => return result,
+            }
+        }
+    }
 
-                let mut decompressed = Vec::with_capacity(0);
-                decompressed.try_reserve(decompressed_size as usize)?;
-                decompressed.resize(decompressed_size as usize, 0);
-
-                BzDecoder::new(aggregation.deref()).read_exact(&mut decompressed)?;
+    /// Makes one attempt at `query`, giving up after `timeout`.
+    fn attempt(
+        &self,
+        addr: SocketAddr,
+        query: &mut Query,
+        timeout: Duration,
+    ) -> Result<Timed<Vec<u8>>> {
+        self.socket
+            .send_to(query.request(), addr)
+            .map_err(|err| query.send_error(addr, err.into()))?;
+        let mut sent = Instant::now();
+        let deadline = sent + timeout;
 
-                if CRC32.checksum(&decompressed) != checksum {
-                    return Err(Error::CheckSumMismatch);
+        let mut data = vec![0; self.max_size];
+        let mut rtt = None;
+
+        loop {
+            data.resize(query.recv_size(), 0);
+            let read = self
+                .recv(&mut data, deadline)
+                .map_err(|err| query.recv_error(addr, err))?;
+            let elapsed = *rtt.get_or_insert_with(|| sent.elapsed());
+
+            match query
+                .push(&data[..read])
+                .map_err(|err| query.error(addr, err))?
+            {
+                QueryStatus::Pending => {}
+                QueryStatus::Resend => {
+                    if let Some(challenges) = &self.challenges {
+                        challenges.update(addr, query);
+                    }
+                    self.socket
+                        .send_to(query.request(), addr)
+                        .map_err(|err| query.send_error(addr, err.into()))?;
+                    sent = Instant::now();
+                    rtt = None;
+                }
+                QueryStatus::Complete(value) => {
+                    return Ok(Timed {
+                        value,
+                        rtt: elapsed,
+                    })
                 }
-
-                Ok(decompressed)
-            } else {
-                Ok(aggregation)
             }
-        } else {
-            Err(Error::InvalidResponse)
         }
     }
+}
+
+// implement info, players, rules methods
+impl A2SClient {
+    pub fn info<A: ToSocketAddrs>(&self, addr: A) -> Result<Info> {
+        self.info_timed(addr).map(|timed| timed.value)
+    }
 
-    fn do_challenge_request<A: ToSocketAddrs>(&self, addr: A, header: &[u8]) -> Result<Vec<u8>> {
-        let packet = Vec::with_capacity(9);
-        let mut packet = Cursor::new(packet);
+    /// Queries A2S_INFO, along with the round-trip time.
+    pub fn info_timed<A: ToSocketAddrs>(&self, addr: A) -> Result<Timed<Info>> {
+        self.do_challenge_request(addr, QueryKind::Info, |data| {
+            Info::from_cursor(Cursor::new(data))
+        })
+    }
 
-        packet.write_all(header)?;
-        packet.write_i32::<LittleEndian>(-1)?;
+    /// Queries A2S_INFO, parsing the response leniently, see
+    /// [`Info::from_cursor_lenient`].
+    pub fn info_lenient<A: ToSocketAddrs>(&self, addr: A) -> Result<Parsed<Info>> {
+        self.do_challenge_request(addr, QueryKind::Info, |data| {
+            Info::from_cursor_lenient(Cursor::new(data))
+        })
+        .map(|timed| timed.value)
+    }
 
-        let data = self.send(packet.get_ref(), &addr)?;
-        let mut data = Cursor::new(data);
+    /// Queries A2S_INFO and accepts both the Source and the obsolete GoldSrc
+    /// response format.
+    pub fn server_info<A: ToSocketAddrs>(&self, addr: A) -> Result<ServerInfo> {
+        self.do_challenge_request(addr, QueryKind::Info, |data| {
+            ServerInfo::from_cursor(Cursor::new(data))
+        })
+        .map(|timed| timed.value)
+    }
 
-        let header = data.read_u8()?;
-        if header != b'A' {
-            return Err(Error::InvalidResponse);
-        }
+    pub fn players<A: ToSocketAddrs>(&self, addr: A) -> Result<Vec<Player>> {
+        self.players_timed(addr).map(|timed| timed.value)
+    }
 
-        let challenge = data.read_i32::<LittleEndian>()?;
+    /// Queries A2S_PLAYER, along with the round-trip time.
+    pub fn players_timed<A: ToSocketAddrs>(&self, addr: A) -> Result<Timed<Vec<Player>>> {
+        self.do_challenge_request(addr, QueryKind::Players, |data| {
+            Player::from_cursor(Cursor::new(data), self.app_id)
+        })
+    }
 
-        packet.set_position(5);
-        packet.write_i32::<LittleEndian>(challenge)?;
-        let data = self.send(packet.get_ref(), &addr)?;
+    /// Queries A2S_PLAYER, along with the player count the response announced,
+    /// see [`Player::from_cursor_counted`].
+    pub fn players_counted<A: ToSocketAddrs>(&self, addr: A) -> Result<Counted<Player>> {
+        self.do_challenge_request(addr, QueryKind::Players, |data| {
+            Player::from_cursor_counted(Cursor::new(data), self.app_id)
+        })
+        .map(|timed| timed.value)
+    }
 
-        Ok(data)
+    /// Queries A2S_PLAYER, parsing the response leniently, see
+    /// [`Player::from_cursor_lenient`].
+    pub fn players_lenient<A: ToSocketAddrs>(&self, addr: A) -> Result<Parsed<Vec<Player>>> {
+        self.do_challenge_request(addr, QueryKind::Players, |data| {
+            Player::from_cursor_lenient(Cursor::new(data), self.app_id)
+        })
+        .map(|timed| timed.value)
     }
-}
 
-// implement info, players, rules methods
-impl A2SClient {
-    pub fn info<A: ToSocketAddrs>(&self, addr: A) -> Result<Info> {
-        let response = self.send(&INFO_REQUEST, &addr)?;
+    pub fn rules<A: ToSocketAddrs>(&self, addr: A) -> Result<Vec<Rule>> {
+        self.rules_timed(addr).map(|timed| timed.value)
+    }
 
-        let mut packet = Cursor::new(&response);
+    /// Queries A2S_RULES, along with the round-trip time.
+    pub fn rules_timed<A: ToSocketAddrs>(&self, addr: A) -> Result<Timed<Vec<Rule>>> {
+        self.do_challenge_request(addr, QueryKind::Rules, |data| {
+            Rule::from_cursor(Cursor::new(data))
+        })
+    }
 
-        let header = packet.read_u8()?;
-        if header == b'A' {
-            let challenge = packet.read_i32::<LittleEndian>()?;
+    /// Queries A2S_RULES, along with the rule count the response announced,
+    /// see [`Rule::from_cursor_counted`].
+    pub fn rules_counted<A: ToSocketAddrs>(&self, addr: A) -> Result<Counted<Rule>> {
+        self.do_challenge_request(addr, QueryKind::Rules, |data| {
+            Rule::from_cursor_counted(Cursor::new(data))
+        })
+        .map(|timed| timed.value)
+    }
 
-            let mut query = Vec::with_capacity(29);
-            query.write_all(&INFO_REQUEST)?;
-            query.write_i32::<LittleEndian>(challenge)?;
+    /// Queries A2S_RULES, parsing the response leniently, see
+    /// [`Rule::from_cursor_lenient`].
+    pub fn rules_lenient<A: ToSocketAddrs>(&self, addr: A) -> Result<Parsed<Vec<Rule>>> {
+        self.do_challenge_request(addr, QueryKind::Rules, |data| {
+            Rule::from_cursor_lenient(Cursor::new(data))
+        })
+        .map(|timed| timed.value)
+    }
 
-            let data = self.send(&query, addr)?;
-            Info::from_cursor(Cursor::new(data))
-        } else {
-            Info::from_cursor(Cursor::new(response))
-        }
+    /// Queries A2S_RULES, keeping names and values as sent, see
+    /// [`RawRule::from_cursor`].
+    pub fn rules_raw<A: ToSocketAddrs>(&self, addr: A) -> Result<Vec<RawRule>> {
+        self.do_challenge_request(addr, QueryKind::Rules, |data| {
+            RawRule::from_cursor(Cursor::new(data))
+        })
+        .map(|timed| timed.value)
     }
 
-    pub fn players<A: ToSocketAddrs>(&self, addr: A) -> Result<Vec<Player>> {
-        let data = self.do_challenge_request(addr, &PLAYER_REQUEST)?;
-        Player::from_cursor(Cursor::new(data), self.app_id)
+    /// Measures the round-trip time with A2A_PING.
+    ///
+    /// A2A_PING is deprecated and most Source servers don't answer it, see
+    /// [`A2SClient::latency`].
+    pub fn ping<A: ToSocketAddrs>(&self, addr: A) -> Result<Duration> {
+        self.do_challenge_request(addr, QueryKind::Ping, |data| {
+            read_header(&mut Cursor::new(data), PING_RESPONSE)
+        })
+        .map(|timed| timed.rtt)
     }
 
-    pub fn rules<A: ToSocketAddrs>(&self, addr: A) -> Result<Vec<Rule>> {
-        let data = self.do_challenge_request(addr, &RULES_REQUEST)?;
-        Rule::from_cursor(Cursor::new(data))
+    /// Measures the round-trip time with A2A_PING, or with A2S_INFO when the
+    /// server doesn't answer A2A_PING in time.
+    pub fn latency<A: ToSocketAddrs>(&self, addr: A) -> Result<Duration> {
+        match self.ping(&addr) {
+            Err(err) if retry::timed_out(&err) => Ok(self.info_timed(&addr)?.rtt),
+            result => result,
+        }
     }
 }
@@ -0,0 +1,28 @@
+use crate::client::A2SClient;
+use crate::errors::Result;
+use crate::packet::Engine;
+use crate::rcon::goldsrc;
+use std::net::ToSocketAddrs;
+
+impl A2SClient {
+    /// Executes `command` over GoldSrc UDP RCON, returning its output.
+    ///
+    /// A fresh challenge is requested for every command, and output keeps
+    /// being gathered until no more arrives within
+    /// [`PRINT_GAP`](goldsrc::PRINT_GAP).
+    pub fn goldsrc_rcon<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        password: &str,
+        command: &str,
+    ) -> Result<String> {
+        let response = self.send_as(goldsrc::CHALLENGE_RCON_REQUEST, &addr, Engine::GoldSrc)?;
+        let challenge = goldsrc::parse_challenge(&response)?;
+
+        let request = goldsrc::command_request(&challenge, password, command)?;
+        let responses =
+            self.send_gather(&request, &addr, Engine::GoldSrc, Some(goldsrc::PRINT_GAP))?;
+
+        goldsrc::parse_responses(&responses)
+    }
+}
@@ -0,0 +1,65 @@
+use crate::client::A2SClient;
+use crate::errors::Result;
+use crate::query::QueryKind;
+use crate::types::snapshot;
+use crate::types::{Info, Player, Rule, ServerSnapshot, Timed};
+use std::io::Cursor;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::time::SystemTime;
+
+impl A2SClient {
+    /// Queries A2S_INFO, A2S_PLAYER and A2S_RULES one after the other,
+    /// reusing the challenge the server issued to the first of them.
+    ///
+    /// Fails if A2S_INFO does, players and rules are left out of the
+    /// snapshot along with the error when their query fails.
+    pub fn snapshot<A: ToSocketAddrs>(&self, addr: A) -> Result<ServerSnapshot> {
+        let addr = Self::resolve(addr)?;
+        let queried_at = SystemTime::now();
+        let mut challenge = None;
+
+        let info = self.snapshot_query(addr, QueryKind::Info, &mut challenge, |data| {
+            Info::from_cursor(Cursor::new(data))
+        })?;
+        let players = self.snapshot_query(addr, QueryKind::Players, &mut challenge, |data| {
+            Player::from_cursor(Cursor::new(data), self.app_id)
+        });
+        let rules = self.snapshot_query(addr, QueryKind::Rules, &mut challenge, |data| {
+            Rule::from_cursor(Cursor::new(data))
+        });
+
+        let (players, players_error) = snapshot::part(players);
+        let (rules, rules_error) = snapshot::part(rules);
+
+        Ok(ServerSnapshot {
+            info: info.value,
+            players,
+            players_error,
+            rules,
+            rules_error,
+            rtt: info.rtt,
+            queried_at,
+        })
+    }
+
+    fn snapshot_query<T>(
+        &self,
+        addr: SocketAddr,
+        kind: QueryKind,
+        challenge: &mut Option<i32>,
+        parse: impl FnOnce(Vec<u8>) -> Result<T>,
+    ) -> Result<Timed<T>> {
+        let mut query = self.query(kind, addr);
+        if let Some(challenge) = *challenge {
+            query.use_challenge(challenge);
+        }
+
+        let result = self.execute(addr, &mut query);
+        *challenge = query.challenge().or(*challenge);
+        let data = result?;
+        Ok(Timed {
+            value: parse(data.value).map_err(|err| query.error(addr, err))?,
+            rtt: data.rtt,
+        })
+    }
+}
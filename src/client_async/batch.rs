@@ -0,0 +1,380 @@
+use crate::client_async::limit::{Limiter, Permit, TryAcquire};
+use crate::client_async::A2SClientAsync;
+use crate::errors::{self, Error, Result};
+use crate::query::{ChallengeCache, Query, QueryKind, QueryStatus};
+use crate::retry::{Backoff, RetryPolicy};
+use crate::types::{Info, Player, Rule};
+use futures::stream::{self, Stream};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::Cursor;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::time::{self, Instant};
+
+struct InFlight {
+    query: Query,
+    /// When the query times out, or is resent if `resend` is set
+    deadline: Instant,
+    /// End of the current attempt, set once its request is sent
+    expires: Option<Instant>,
+    resend: bool,
+    backoff: Backoff,
+    _permit: Permit,
+}
+
+impl InFlight {
+    fn new(query: Query, permit: Permit, retry: RetryPolicy) -> Self {
+        Self {
+            query,
+            deadline: Instant::now(),
+            expires: None,
+            resend: false,
+            backoff: Backoff::new(retry),
+            _permit: permit,
+        }
+    }
+}
+
+/// Parses the response payload of a batch query.
+type Parse<T> = Box<dyn Fn(Vec<u8>) -> Result<T> + Send + Sync>;
+
+/// State of a batch: every server has at most one query in flight, so
+/// datagrams are routed to their query by source address.
+struct Batch<T> {
+    socket: UdpSocket,
+    timeout: Duration,
+    fragment_timeout: Option<Duration>,
+    max_size: usize,
+    retry: RetryPolicy,
+    limiter: Arc<Limiter>,
+    challenges: Option<Arc<ChallengeCache>>,
+    parse: Parse<T>,
+    unsent: VecDeque<(SocketAddr, Query)>,
+    /// Requests waiting for their turn under the packet rate limit, with
+    /// when they may be sent
+    queued: VecDeque<(Instant, SocketAddr, InFlight)>,
+    in_flight: HashMap<SocketAddr, InFlight>,
+    results: VecDeque<(SocketAddr, Result<T>)>,
+}
+
+impl<T> Batch<T> {
+    /// When a query waiting for its next datagram since now times out.
+    fn deadline(&self, expires: Instant) -> Instant {
+        match self.fragment_timeout {
+            Some(fragment_timeout) => expires.min(Instant::now() + fragment_timeout),
+            None => expires,
+        }
+    }
+
+    /// Queues the request of `in_flight` for sending once the rate limit
+    /// allows it, so waiting never holds up receiving.
+    fn send(&mut self, addr: SocketAddr, in_flight: InFlight) {
+        let at = self.limiter.reserve();
+        self.queued.push_back((at, addr, in_flight));
+    }
+
+    /// Sends the queued requests whose turn has come.
+    async fn flush(&mut self) {
+        let now = Instant::now();
+        while let Some((at, _, _)) = self.queued.front() {
+            if *at > now {
+                break;
+            }
+            if let Some((_, addr, in_flight)) = self.queued.pop_front() {
+                self.transmit(addr, in_flight).await;
+            }
+        }
+    }
+
+    async fn transmit(&mut self, addr: SocketAddr, mut in_flight: InFlight) {
+        match self.socket.send_to(in_flight.query.request(), addr).await {
+            Ok(_) => {
+                let expires = *in_flight.expires.get_or_insert_with(|| {
+                    Instant::now() + in_flight.backoff.timeout(self.timeout)
+                });
+                in_flight.deadline = self.deadline(expires);
+                in_flight.resend = false;
+                self.in_flight.insert(addr, in_flight);
+            }
+            Err(err) => {
+                let err = in_flight.query.send_error(addr, err.into());
+                self.results.push_back((addr, Err(err)))
+            }
+        }
+    }
+
+    fn route(&mut self, addr: SocketAddr, datagram: &[u8]) {
+        let mut in_flight = match self.in_flight.remove(&addr) {
+            Some(in_flight) => in_flight,
+            // Late or unsolicited datagram
+            None => return,
+        };
+
+        match in_flight.query.push(datagram) {
+            Ok(QueryStatus::Pending) => {
+                // Late fragments of an earlier attempt don't delay the retry
+                if let Some(expires) = in_flight.expires {
+                    in_flight.deadline = self.deadline(expires);
+                }
+                self.in_flight.insert(addr, in_flight);
+            }
+            Ok(QueryStatus::Resend) => {
+                if let Some(challenges) = &self.challenges {
+                    challenges.update(addr, &in_flight.query);
+                }
+                self.send(addr, in_flight)
+            }
+            Ok(QueryStatus::Complete(payload)) => {
+                let result = (self.parse)(payload).map_err(|err| in_flight.query.error(addr, err));
+                self.results.push_back((addr, result))
+            }
+            // A late fragment of an earlier attempt started a response under
+            // its split-packet ID, which is dropped for the current one
+            Err(Error::MismatchID) if in_flight.backoff.retried() => {
+                in_flight.query.retry();
+                self.in_flight.insert(addr, in_flight);
+                self.route(addr, datagram)
+            }
+            Err(err) => {
+                let err = in_flight.query.error(addr, err);
+                self.results.push_back((addr, Err(err)))
+            }
+        }
+    }
+
+    fn expire(&mut self) {
+        let now = Instant::now();
+        let expired: Vec<SocketAddr> = self
+            .in_flight
+            .iter()
+            .filter(|(_, in_flight)| in_flight.deadline <= now)
+            .map(|(addr, _)| *addr)
+            .collect();
+
+        for addr in expired {
+            let mut in_flight = match self.in_flight.remove(&addr) {
+                Some(in_flight) => in_flight,
+                None => continue,
+            };
+
+            if in_flight.resend {
+                self.send(addr, in_flight);
+                continue;
+            }
+
+            if in_flight.query.fallback() {
+                in_flight.expires = None;
+                self.send(addr, in_flight);
+                continue;
+            }
+
+            let err = in_flight.query.recv_error(addr, Error::ErrTimeout);
+            match in_flight.backoff.retry(err) {
+                Ok(delay) => {
+                    in_flight.query.retry();
+                    in_flight.deadline = now + delay;
+                    in_flight.expires = None;
+                    in_flight.resend = true;
+                    self.in_flight.insert(addr, in_flight);
+                }
+                Err(err) => self.results.push_back((addr, Err(err))),
+            }
+        }
+    }
+
+    fn fail_all(&mut self, err: std::io::Error) {
+        let queries = self
+            .unsent
+            .drain(..)
+            .chain(self.queued.drain(..).map(|(_, addr, i)| (addr, i.query)))
+            .chain(self.in_flight.drain().map(|(addr, i)| (addr, i.query)))
+            .collect::<Vec<_>>();
+
+        for (addr, mut query) in queries {
+            let err = std::io::Error::new(err.kind(), err.to_string());
+            let err = query.recv_error(addr, err.into());
+            self.results.push_back((addr, Err(err)));
+        }
+    }
+
+    /// Sends as many unsent queries as the limits allow, keeping at least one
+    /// query in flight while any is left.
+    async fn start(&mut self) {
+        let mut host_busy = VecDeque::new();
+
+        while let Some((addr, query)) = self.unsent.pop_front() {
+            match self.limiter.try_acquire(addr.ip()) {
+                TryAcquire::Acquired(permit) => {
+                    let in_flight = InFlight::new(query, permit, self.retry);
+                    self.send(addr, in_flight)
+                }
+                TryAcquire::HostBusy => host_busy.push_back((addr, query)),
+                TryAcquire::Full => {
+                    self.unsent.push_front((addr, query));
+                    break;
+                }
+            }
+        }
+
+        // Servers skipped for their IP address keep their place in line
+        host_busy.append(&mut self.unsent);
+        self.unsent = host_busy;
+
+        // Other calls sharing the limiter may hold every permit
+        if self.in_flight.is_empty() && self.queued.is_empty() && self.results.is_empty() {
+            if let Some((addr, query)) = self.unsent.pop_front() {
+                let permit = self.limiter.acquire(addr.ip()).await;
+                let in_flight = InFlight::new(query, permit, self.retry);
+                self.send(addr, in_flight);
+            }
+        }
+    }
+
+    async fn next(mut self) -> Option<((SocketAddr, Result<T>), Self)> {
+        let mut data = vec![0; self.max_size];
+
+        loop {
+            if let Some(result) = self.results.pop_front() {
+                return Some((result, self));
+            }
+
+            self.start().await;
+            self.flush().await;
+            if !self.results.is_empty() {
+                continue;
+            }
+
+            let deadline = match self
+                .in_flight
+                .values()
+                .map(|i| i.deadline)
+                .chain(self.queued.front().map(|(at, _, _)| *at))
+                .min()
+            {
+                Some(deadline) => deadline,
+                None => return None,
+            };
+
+            match time::timeout_at(deadline, self.socket.recv_from(&mut data)).await {
+                Ok(Ok((read, addr))) => self.route(addr, &data[..read]),
+                // Such as the ICMP port unreachable of a single server, which
+                // Windows reports as ConnectionReset
+                Ok(Err(err)) if errors::is_transient(&err) => {}
+                Ok(Err(err)) => self.fail_all(err),
+                Err(_) => self.expire(),
+            }
+        }
+    }
+}
+
+impl A2SClientAsync {
+    /// Queries many servers from a single socket, yielding the response of
+    /// every server as it completes, parsed with `parse`.
+    async fn batch<I, T>(
+        &self,
+        kind: QueryKind,
+        addrs: I,
+        parse: Parse<T>,
+    ) -> Result<impl Stream<Item = (SocketAddr, Result<T>)>>
+    where
+        I: IntoIterator<Item = SocketAddr>,
+    {
+        let socket = UdpSocket::bind("0.0.0.0:0").await?;
+        let mut seen = HashSet::new();
+        let unsent = addrs
+            .into_iter()
+            .filter(|addr| seen.insert(*addr))
+            .map(|addr| (addr, self.query(kind, addr)))
+            .collect();
+
+        let batch = Batch {
+            socket,
+            timeout: self.timeout,
+            fragment_timeout: self.fragment_timeout,
+            max_size: self.max_size,
+            retry: self.retry,
+            limiter: self.limiter.clone(),
+            challenges: self.challenges.clone(),
+            parse,
+            unsent,
+            queued: VecDeque::new(),
+            in_flight: HashMap::new(),
+            results: VecDeque::new(),
+        };
+
+        Ok(stream::unfold(batch, Batch::next))
+    }
+
+    /// Queries A2S_INFO of many servers from a single socket.
+    ///
+    /// Results are yielded in completion order, each with the address it
+    /// belongs to. Duplicate addresses are queried once.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use futures::StreamExt;
+    /// use std::net::SocketAddr;
+    ///
+    /// # async fn run(addrs: Vec<SocketAddr>) {
+    /// let client = crowbar_a2s::Builder::new().build_async().unwrap();
+    /// let results = client.batch_info(addrs).await.unwrap();
+    /// let mut results = std::pin::pin!(results);
+    ///
+    /// while let Some((addr, info)) = results.next().await {
+    ///     println!("{}: {:?}", addr, info);
+    /// }
+    /// # }
+    /// ```
+    pub async fn batch_info<I>(
+        &self,
+        addrs: I,
+    ) -> Result<impl Stream<Item = (SocketAddr, Result<Info>)>>
+    where
+        I: IntoIterator<Item = SocketAddr>,
+    {
+        self.batch(
+            QueryKind::Info,
+            addrs,
+            Box::new(|data| Info::from_cursor(Cursor::new(data))),
+        )
+        .await
+    }
+
+    /// Queries A2S_PLAYER of many servers from a single socket, see
+    /// [`A2SClientAsync::batch_info`].
+    pub async fn batch_players<I>(
+        &self,
+        addrs: I,
+    ) -> Result<impl Stream<Item = (SocketAddr, Result<Vec<Player>>)>>
+    where
+        I: IntoIterator<Item = SocketAddr>,
+    {
+        let app_id = self.app_id;
+        self.batch(
+            QueryKind::Players,
+            addrs,
+            Box::new(move |data| Player::from_cursor(Cursor::new(data), app_id)),
+        )
+        .await
+    }
+
+    /// Queries A2S_RULES of many servers from a single socket, see
+    /// [`A2SClientAsync::batch_info`].
+    pub async fn batch_rules<I>(
+        &self,
+        addrs: I,
+    ) -> Result<impl Stream<Item = (SocketAddr, Result<Vec<Rule>>)>>
+    where
+        I: IntoIterator<Item = SocketAddr>,
+    {
+        self.batch(
+            QueryKind::Rules,
+            addrs,
+            Box::new(|data| Rule::from_cursor(Cursor::new(data))),
+        )
+        .await
+    }
+}
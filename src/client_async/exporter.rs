@@ -0,0 +1,230 @@
+use crate::client_async::A2SClientAsync;
+use crate::errors::Result;
+use crate::types::{Info, Timed};
+use futures::future::{self, Either};
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::{self, Instant, MissedTickBehavior};
+
+/// Time a scraper may take to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Largest request head accepted, scrapers send a few hundred bytes.
+const MAX_REQUEST_SIZE: usize = 8192;
+
+/// Value of a gauge for a server which answered.
+type Gauge = fn(&Timed<Info>) -> f64;
+
+/// Gauges exported for servers which answered, besides `a2s_up`.
+const GAUGES: [(&str, &str, Gauge); 4] = [
+    ("a2s_players", "Number of players on the server.", |info| {
+        info.value.players as f64
+    }),
+    ("a2s_max_players", "Maximum number of players.", |info| {
+        info.value.max_players as f64
+    }),
+    ("a2s_bots", "Number of bots on the server.", |info| {
+        info.value.bots as f64
+    }),
+    ("a2s_rtt_seconds", "Round-trip time of A2S_INFO.", |info| {
+        info.rtt.as_secs_f64()
+    }),
+];
+
+/// Exporter polls servers with A2S_INFO and serves the results as
+/// Prometheus metrics:
+///
+/// - `a2s_up`, whether the server answered the last poll
+/// - `a2s_players`, `a2s_max_players` and `a2s_bots`
+/// - `a2s_rtt_seconds`, the round-trip time of the last poll
+///
+/// Every metric is labelled with the `server` address. All but `a2s_up` are
+/// also labelled with the `map`, `game` and `version` of the server, and
+/// left out while it's down.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use crowbar_a2s::Exporter;
+/// use std::time::Duration;
+/// use tokio::net::TcpListener;
+///
+/// # async fn run() {
+/// let client = crowbar_a2s::Builder::new().build_async().unwrap();
+/// let mut exporter = Exporter::new(client);
+/// exporter
+///     .interval(Duration::from_secs(15))
+///     .server("127.0.0.1:27015".parse().unwrap());
+///
+/// let listener = TcpListener::bind("127.0.0.1:9841").await.unwrap();
+/// exporter.serve(listener).await.unwrap();
+/// # }
+/// ```
+pub struct Exporter {
+    client: A2SClientAsync,
+    interval: Duration,
+    servers: Vec<(SocketAddr, Option<Timed<Info>>)>,
+}
+
+impl Exporter {
+    pub fn new(client: A2SClientAsync) -> Self {
+        Self {
+            client,
+            interval: Duration::from_secs(15),
+            servers: Vec::new(),
+        }
+    }
+
+    /// Time between polls of [`Exporter::serve`]. Defaults to 15 seconds.
+    pub fn interval(&mut self, interval: Duration) -> &mut Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Adds a server to export.
+    pub fn server(&mut self, addr: SocketAddr) -> &mut Self {
+        if self.servers.iter().all(|(server, _)| *server != addr) {
+            self.servers.push((addr, None));
+        }
+        self
+    }
+
+    /// Polls every server once, concurrently.
+    pub async fn poll(&mut self) {
+        let client = &self.client;
+        let infos = future::join_all(
+            self.servers
+                .iter()
+                .map(|(addr, _)| client.info_timed(*addr)),
+        )
+        .await;
+
+        for ((_, state), info) in self.servers.iter_mut().zip(infos) {
+            *state = info.ok();
+        }
+    }
+
+    /// Metrics as of the last poll, in the Prometheus text format.
+    pub fn metrics(&self) -> String {
+        let mut out = String::new();
+
+        metric(&mut out, "a2s_up", "Whether the server answered A2S_INFO.");
+        for (addr, state) in &self.servers {
+            let up = if state.is_some() { 1 } else { 0 };
+            let _ = writeln!(out, "a2s_up{{server=\"{}\"}} {}", addr, up);
+        }
+
+        for (name, help, value) in GAUGES {
+            metric(&mut out, name, help);
+            for (addr, state) in &self.servers {
+                if let Some(info) = state {
+                    let _ = writeln!(out, "{}{{{}}} {}", name, labels(*addr, info), value(info));
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Polls the servers every interval and serves the metrics at
+    /// `/metrics` to HTTP clients of `listener`, until accepting a
+    /// connection fails.
+    ///
+    /// The first poll completes before any request is answered. Requests are
+    /// answered one at a time, which is plenty for a few scrapers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the interval is zero.
+    pub async fn serve(mut self, listener: TcpListener) -> Result<()> {
+        self.poll().await;
+        let metrics = Mutex::new(self.metrics());
+
+        let polls = std::pin::pin!(self.polls(&metrics));
+        let scrapes = std::pin::pin!(scrapes(&listener, &metrics));
+
+        match future::select(polls, scrapes).await {
+            Either::Left(((), _)) => Ok(()),
+            Either::Right((result, _)) => result,
+        }
+    }
+
+    async fn polls(&mut self, metrics: &Mutex<String>) {
+        let mut ticker = time::interval_at(Instant::now() + self.interval, self.interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            self.poll().await;
+            let rendered = self.metrics();
+            *metrics.lock().unwrap_or_else(|e| e.into_inner()) = rendered;
+        }
+    }
+}
+
+fn metric(out: &mut String, name: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+}
+
+fn labels(addr: SocketAddr, info: &Timed<Info>) -> String {
+    format!(
+        "server=\"{}\",map=\"{}\",game=\"{}\",version=\"{}\"",
+        addr,
+        escape(&info.value.map.to_string_lossy()),
+        escape(&info.value.game.to_string_lossy()),
+        escape(&info.value.version.to_string_lossy())
+    )
+}
+
+/// Escapes a label value, as server-controlled strings can contain anything.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+async fn scrapes(listener: &TcpListener, metrics: &Mutex<String>) -> Result<()> {
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let body = metrics.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        // A misbehaving scraper only fails its own request
+        let _ = time::timeout(REQUEST_TIMEOUT, respond(stream, &body)).await;
+    }
+}
+
+async fn respond(mut stream: TcpStream, metrics: &str) -> Result<()> {
+    let mut request = Vec::new();
+    let mut data = [0; 1024];
+
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+        let read = stream.read(&mut data).await?;
+        if read == 0 || request.len() + read > MAX_REQUEST_SIZE {
+            return Ok(());
+        }
+        request.extend_from_slice(&data[..read]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut line = request.split_whitespace();
+    let (status, body) = match (line.next(), line.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", metrics),
+        (Some("GET"), _) => ("404 Not Found", "Not found, see /metrics\n"),
+        _ => ("405 Method Not Allowed", "Method not allowed\n"),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
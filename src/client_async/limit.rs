@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::{self, Instant};
+
+/// Limits configured on the [`Builder`](crate::Builder) for bulk scans.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Limits {
+    pub(crate) max_in_flight: Option<usize>,
+    pub(crate) packets_per_second: Option<u32>,
+    pub(crate) per_host: Option<usize>,
+}
+
+/// Token bucket refilled at `rate` tokens per second, holding up to one
+/// second worth of tokens.
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    /// Takes a token, returning how long to wait before it may be used.
+    ///
+    /// Tokens may be taken ahead of time, which makes the bucket go negative
+    /// and later callers wait in order.
+    fn take(&mut self) -> Duration {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate.max(1.0));
+        self.last = now;
+
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+/// Permission to have one query in flight.
+#[derive(Debug)]
+pub(crate) struct Permit {
+    _in_flight: Option<OwnedSemaphorePermit>,
+    _host: Option<OwnedSemaphorePermit>,
+}
+
+pub(crate) enum TryAcquire {
+    Acquired(Permit),
+    /// The server's IP address has the maximum number of queries in flight.
+    HostBusy,
+    /// The client has the maximum number of queries in flight.
+    Full,
+}
+
+/// Shared by every query of an [`A2SClientAsync`](crate::A2SClientAsync),
+/// so limits hold across concurrent calls.
+#[derive(Debug)]
+pub(crate) struct Limiter {
+    in_flight: Option<Arc<Semaphore>>,
+    per_host: Option<usize>,
+    hosts: Mutex<HashMap<IpAddr, Arc<Semaphore>>>,
+    bucket: Option<Mutex<TokenBucket>>,
+}
+
+impl Limiter {
+    pub(crate) fn new(limits: Limits) -> Self {
+        Self {
+            in_flight: limits
+                .max_in_flight
+                .map(|n| Arc::new(Semaphore::new(n.max(1)))),
+            per_host: limits.per_host,
+            hosts: Mutex::new(HashMap::new()),
+            bucket: limits
+                .packets_per_second
+                .filter(|rate| *rate > 0)
+                .map(|rate| {
+                    Mutex::new(TokenBucket {
+                        rate: rate.into(),
+                        tokens: rate.into(),
+                        last: Instant::now(),
+                    })
+                }),
+        }
+    }
+
+    fn host(&self, ip: IpAddr) -> Option<Arc<Semaphore>> {
+        let limit = self.per_host?.max(1);
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+
+        if !hosts.contains_key(&ip) {
+            // Forget hosts without queries in flight, their permits hold a
+            // reference to the semaphore
+            hosts.retain(|_, semaphore| Arc::strong_count(semaphore) > 1);
+        }
+
+        Some(
+            hosts
+                .entry(ip)
+                .or_insert_with(|| Arc::new(Semaphore::new(limit)))
+                .clone(),
+        )
+    }
+
+    /// Waits until a query to `ip` may be started.
+    pub(crate) async fn acquire(&self, ip: IpAddr) -> Permit {
+        let in_flight = match &self.in_flight {
+            Some(semaphore) => semaphore.clone().acquire_owned().await.ok(),
+            None => None,
+        };
+        let host = match self.host(ip) {
+            Some(semaphore) => semaphore.acquire_owned().await.ok(),
+            None => None,
+        };
+
+        Permit {
+            _in_flight: in_flight,
+            _host: host,
+        }
+    }
+
+    /// Starts a query to `ip` if the limits allow it right now.
+    pub(crate) fn try_acquire(&self, ip: IpAddr) -> TryAcquire {
+        let in_flight = match &self.in_flight {
+            Some(semaphore) => match semaphore.clone().try_acquire_owned() {
+                Ok(permit) => Some(permit),
+                Err(_) => return TryAcquire::Full,
+            },
+            None => None,
+        };
+        let host = match self.host(ip) {
+            Some(semaphore) => match semaphore.try_acquire_owned() {
+                Ok(permit) => Some(permit),
+                Err(_) => return TryAcquire::HostBusy,
+            },
+            None => None,
+        };
+
+        TryAcquire::Acquired(Permit {
+            _in_flight: in_flight,
+            _host: host,
+        })
+    }
+
+    /// Takes a packet's turn, returning when it may be sent.
+    pub(crate) fn reserve(&self) -> Instant {
+        match &self.bucket {
+            Some(bucket) => {
+                Instant::now() + bucket.lock().unwrap_or_else(|e| e.into_inner()).take()
+            }
+            None => Instant::now(),
+        }
+    }
+
+    /// Waits until a packet may be sent.
+    pub(crate) async fn packet(&self) {
+        if self.bucket.is_some() {
+            time::sleep_until(self.reserve()).await;
+        }
+    }
+}
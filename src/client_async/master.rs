@@ -0,0 +1,85 @@
+use crate::client_async::limit::Limiter;
+use crate::client_async::A2SClientAsync;
+use crate::errors::{Error, Result};
+use crate::master::{Filter, MasterQuery, Region};
+use futures::stream::{self, Stream};
+use std::net::{SocketAddr, SocketAddrV4};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{lookup_host, ToSocketAddrs, UdpSocket};
+use tokio::time;
+
+struct MasterServers {
+    socket: UdpSocket,
+    master: SocketAddr,
+    timeout: Duration,
+    max_size: usize,
+    limiter: Arc<Limiter>,
+    query: MasterQuery,
+}
+
+impl MasterServers {
+    async fn request_page(&mut self) -> Result<()> {
+        let request = match self.query.next_request() {
+            Some(request) => request,
+            None => return Ok(()),
+        };
+        self.limiter.packet().await;
+        future_timeout!(self.timeout, self.socket.send_to(&request, self.master))?;
+
+        let mut data = vec![0; self.max_size];
+        loop {
+            let (read, from) = future_timeout!(self.timeout, self.socket.recv_from(&mut data))?;
+            if from == self.master {
+                return self.query.push_response(&data[..read]);
+            }
+        }
+    }
+
+    async fn next(mut self) -> Option<(Result<SocketAddrV4>, Option<Self>)> {
+        loop {
+            if let Some(addr) = self.query.pop() {
+                return Some((Ok(addr), Some(self)));
+            }
+            if self.query.is_done() {
+                return None;
+            }
+            if let Err(err) = self.request_page().await {
+                return Some((Err(err), None));
+            }
+        }
+    }
+}
+
+impl A2SClientAsync {
+    /// Lists the servers known to a master server, such as
+    /// [`MASTER_SOURCE`](crate::master::MASTER_SOURCE), matching `filter`.
+    ///
+    /// Pages are requested lazily as the stream is polled. After an error the
+    /// stream ends.
+    pub async fn master_servers<A: ToSocketAddrs>(
+        &self,
+        master: A,
+        region: Region,
+        filter: &Filter,
+    ) -> Result<impl Stream<Item = Result<SocketAddrV4>>> {
+        let master = lookup_host(master)
+            .await?
+            .next()
+            .ok_or(Error::Other("Master server address did not resolve"))?;
+        let socket = UdpSocket::bind("0.0.0.0:0").await?;
+
+        let state = MasterServers {
+            socket,
+            master,
+            timeout: self.timeout,
+            max_size: self.max_size,
+            limiter: self.limiter.clone(),
+            query: MasterQuery::new(region, filter),
+        };
+
+        Ok(stream::unfold(Some(state), |state| async move {
+            state?.next().await
+        }))
+    }
+}
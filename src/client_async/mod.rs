@@ -1,74 +1,20 @@
-use crate::constants::*;
+use crate::client_async::limit::Limiter;
 use crate::errors::{Error, Result};
-
-use crate::types::{Info, INFO_REQUEST};
-
-use crate::types::{Player, PLAYER_REQUEST};
-
-use crate::CRC32;
-use crate::types::{Rule, RULES_REQUEST};
-use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use bzip2::read::BzDecoder;
-use std::io::{Cursor, Read, Write};
-use std::ops::Deref;
+use crate::packet::Engine;
+#[cfg(feature = "rcon")]
+use crate::packet::Reassembler;
+use crate::query::{ChallengeCache, ChallengeMode, Query, QueryKind, QueryStatus};
+use crate::retry::{self, Backoff, RetryPolicy};
+
+use crate::types::{
+    read_header, Counted, Info, Parsed, Player, RawRule, Rule, ServerInfo, Timed, PING_RESPONSE,
+};
+use std::io::Cursor;
+use std::net::SocketAddr;
+use std::sync::Arc;
 use std::time::Duration;
-use tokio::net::{ToSocketAddrs, UdpSocket};
-use tokio::time;
-
-macro_rules! read_buffer_offset {
-    ($buf:expr, $offset:expr, i8) => {
-        $buf[$offset].into()
-    };
-    ($buf:expr, $offset:expr, u8) => {
-        $buf[$offset].into()
-    };
-    ($buf:expr, $offset:expr, i16) => {
-        i16::from_le_bytes([$buf[$offset], $buf[$offset + 1]])
-    };
-    ($buf:expr, $offset:expr, u16) => {
-        u16::from_le_bytes([$buf[$offset], $buf[$offset + 1]])
-    };
-    ($buf:expr, $offset:expr, i32) => {
-        i32::from_le_bytes([
-            $buf[$offset],
-            $buf[$offset + 1],
-            $buf[$offset + 2],
-            $buf[$offset + 3],
-        ])
-    };
-    ($buf:expr, $offset:expr, u32) => {
-        u32::from_le_bytes([
-            $buf[$offset],
-            $buf[$offset + 1],
-            $buf[$offset + 2],
-            $buf[$offset + 3],
-        ])
-    };
-    ($buf:expr, $offset:expr, i64) => {
-        i64::from_le_bytes([
-            $buf[$offset],
-            $buf[$offset + 1],
-            $buf[$offset + 2],
-            $buf[$offset + 3],
-            $buf[$offset + 4],
-            $buf[$offset + 5],
-            $buf[$offset + 6],
-            $buf[$offset + 7],
-        ])
-    };
-    ($buf:expr, $offset:expr, u64) => {
-        u64::from_le_bytes([
-            $buf[$offset],
-            $buf[$offset + 1],
-            $buf[$offset + 2],
-            $buf[$offset + 3],
-            $buf[$offset + 4],
-            $buf[$offset + 5],
-            $buf[$offset + 6],
-            $buf[$offset + 7],
-        ])
-    };
-}
+use tokio::net::{lookup_host, ToSocketAddrs, UdpSocket};
+use tokio::time::{self, Instant};
 
 macro_rules! future_timeout {
     ($timeout:expr, $future:expr) => {
@@ -79,14 +25,26 @@ macro_rules! future_timeout {
     };
 }
 
-/// A2SClient is a synchronous client for the A2S protocol.
-/// It is used to query Source but not GoldSrc servers.
+mod batch;
+pub mod exporter;
+pub(crate) mod limit;
+mod master;
+#[cfg(feature = "rcon")]
+mod rcon;
+mod snapshot;
+pub mod watcher;
+
+/// A2SClientAsync is an asynchronous client for the A2S protocol.
+/// It is used to query Source servers, and GoldSrc servers when built with
+/// [`Engine::GoldSrc`](crate::Engine::GoldSrc).
 ///
 /// # Example
 ///
-/// ```rust
+/// ```rust,no_run
+/// use std::time::Duration;
 /// use crowbar_a2s::{Builder, A2SClientAsync};
 ///
+/// # async fn run() {
 /// let client: A2SClientAsync = crowbar_a2s::Builder::new()
 ///     .max_size(1400)
 ///     .app_id(0)
@@ -95,15 +53,24 @@ macro_rules! future_timeout {
 ///     .unwrap();
 /// let result = client
 ///     .info(&std::env::var("CARGO_TEST_SRCDS_ADDR").unwrap())
+///     .await
 ///     .unwrap();
 ///
-/// println!("Sync: {:?}", result);
+/// println!("Async: {:?}", result);
+/// # }
 /// ```
 pub struct A2SClientAsync {
     pub(crate) timeout: Duration,
+    pub(crate) fragment_timeout: Option<Duration>,
     pub(crate) max_size: usize,
     /// steam app id, if you want to query _The Ship_ servers' players, you need to set this to 2400
     pub(crate) app_id: u16,
+    pub(crate) engine: Engine,
+    pub(crate) retry: RetryPolicy,
+    pub(crate) limiter: Arc<Limiter>,
+    pub(crate) challenges: Option<Arc<ChallengeCache>>,
+    pub(crate) challenge_mode: ChallengeMode,
+    pub(crate) capture: bool,
 }
 
 impl A2SClientAsync {
@@ -117,173 +84,324 @@ impl A2SClientAsync {
         self
     }
 
-    async fn send<A: ToSocketAddrs>(&self, payload: &[u8], addr: A) -> Result<Vec<u8>> {
-        let socket = UdpSocket::bind("0.0.0.0:0").await?;
-        future_timeout!(self.timeout, socket.send_to(payload, addr))?;
-
-        let mut data = vec![0; self.max_size];
-
-        let read = future_timeout!(self.timeout, socket.recv(&mut data))?;
-        data.truncate(read);
-
-        let header = read_buffer_offset!(&data, OFS_HEADER, i32);
+    pub fn engine(&mut self, engine: Engine) -> &mut Self {
+        self.engine = engine;
+        self
+    }
 
-        if header == SINGLE_PACKET {
-            Ok(data[OFS_SP_PAYLOAD..].to_vec())
-        } else if header == MULTI_PACKET {
-            // ID - long (4 bytes)
-            // Total - byte (1 byte)
-            // Number - byte (1 byte)
-            // Size - short (2 bytes)
+    async fn resolve<A: ToSocketAddrs>(addr: A) -> Result<SocketAddr> {
+        lookup_host(addr)
+            .await?
+            .next()
+            .ok_or(Error::Other("Server address did not resolve"))
+    }
 
-            let id = read_buffer_offset!(&data, OFS_MP_ID, i32);
-            let total_packets: usize = data[OFS_MP_SS_TOTAL].into();
-            let switching_size: usize = read_buffer_offset!(&data, OFS_MP_SS_SIZE, u16).into();
+    /// Sends `payload` to `addr` once the packet rate limit allows it.
+    async fn send_to(&self, socket: &UdpSocket, payload: &[u8], addr: SocketAddr) -> Result<()> {
+        self.limiter.packet().await;
+        future_timeout!(self.timeout, socket.send_to(payload, addr))?;
+        Ok(())
+    }
 
-            // Sanity check
-            if (switching_size > self.max_size) || (total_packets > 32) {
-                return Err(Error::InvalidResponse);
-            }
+    /// Receives one datagram before `deadline`, or before the fragment
+    /// timeout if it's sooner.
+    async fn recv(&self, socket: &UdpSocket, data: &mut [u8], deadline: Instant) -> Result<usize> {
+        let wait = match self.fragment_timeout {
+            Some(fragment_timeout) => deadline.min(Instant::now() + fragment_timeout),
+            None => deadline,
+        };
+
+        match time::timeout_at(wait, socket.recv(data)).await {
+            Ok(read) => Ok(read?),
+            Err(_) => Err(Error::ErrTimeout),
+        }
+    }
 
-            let mut packets: Vec<PacketFragment> = Vec::with_capacity(0);
-            packets.try_reserve(total_packets)?;
-            packets.push(PacketFragment {
-                number: data[OFS_MP_SS_NUMBER],
-                // The first packet seems to include a single packet header (0xFFFFFFFF) for some
-                // reason, so we'd rather skip that (hence +4)
-                payload: Vec::from(&data[OFS_MP_SS_PAYLOAD + 4..]),
-            });
-
-            loop {
-                let mut data: Vec<u8> = Vec::with_capacity(0);
-                data.try_reserve(switching_size)?;
-                data.resize(switching_size, 0);
-
-                let read = future_timeout!(self.timeout, socket.recv(&mut data))?;
-                data.truncate(read);
-
-                if data.len() <= 9 {
-                    Err(Error::InvalidResponse)?
-                }
+    /// Sends a request, reassembling the response as split by `engine`.
+    #[cfg(feature = "rcon")]
+    async fn send_as<A: ToSocketAddrs>(
+        &self,
+        payload: &[u8],
+        addr: A,
+        engine: Engine,
+    ) -> Result<Vec<u8>> {
+        self.send_gather(payload, addr, engine, None)
+            .await
+            .map(|mut responses| responses.remove(0))
+    }
 
-                let packet_id = read_buffer_offset!(&data, OFS_MP_ID, i32);
+    /// Sends a request, reassembling the responses as split by `engine`.
+    /// With a `gap`, responses keep being gathered until none arrives within
+    /// `gap` of the last one.
+    #[cfg(feature = "rcon")]
+    async fn send_gather<A: ToSocketAddrs>(
+        &self,
+        payload: &[u8],
+        addr: A,
+        engine: Engine,
+        gap: Option<Duration>,
+    ) -> Result<Vec<Vec<u8>>> {
+        let addr = Self::resolve(addr).await?;
+        let _permit = self.limiter.acquire(addr.ip()).await;
+        let socket = UdpSocket::bind("0.0.0.0:0").await?;
+        self.send_to(&socket, payload, addr).await?;
+        let mut deadline = Instant::now() + self.timeout;
 
-                if packet_id != id {
-                    return Err(Error::MismatchID);
-                }
+        let mut responses = Vec::new();
+        let mut reassembler = Reassembler::with_engine(self.max_size, engine);
+        let mut data = vec![0; self.max_size];
 
-                if id as u32 & 0x80000000 == 0 {
-                    // Uncompressed packet
-                    packets.push(PacketFragment {
-                        number: data[OFS_MP_SS_NUMBER],
-                        payload: Vec::from(&data[OFS_MP_SS_PAYLOAD..]),
-                    });
-                } else {
-                    // BZip2 compressed packet
-                    packets.push(PacketFragment {
-                        number: data[OFS_MP_SS_NUMBER],
-                        payload: Vec::from(&data[OFS_MP_SS_PAYLOAD_BZ2..]),
-                    });
+        loop {
+            data.resize(reassembler.recv_size(), 0);
+            let read = match self.recv(&socket, &mut data, deadline).await {
+                Ok(read) => read,
+                Err(err) if !responses.is_empty() && retry::is_retriable(&err) => {
+                    return Ok(responses)
                 }
-
-                if packets.len() == total_packets {
-                    break;
+                Err(err) => return Err(err),
+            };
+
+            if let Some(payload) = reassembler.push(&data[..read])? {
+                responses.push(payload);
+                match gap {
+                    Some(gap) => deadline = Instant::now() + gap,
+                    None => return Ok(responses),
                 }
             }
+        }
+    }
 
-            packets.sort_by_key(|p| p.number);
+    /// Starts a query to `addr` as configured.
+    fn query(&self, kind: QueryKind, addr: SocketAddr) -> Query {
+        let mut query = Query::new(kind, self.max_size, self.engine);
+        query
+            .challenge_mode(self.challenge_mode)
+            .capture(self.capture);
+        if let Some(challenges) = &self.challenges {
+            challenges.prepare(addr, &mut query);
+        }
+        query
+    }
 
-            let mut aggregation = Vec::with_capacity(0);
-            aggregation.try_reserve(total_packets * self.max_size)?;
+    /// Queries `addr`, parsing the response payload with `parse`.
+    async fn do_challenge_request<A, T>(
+        &self,
+        addr: A,
+        kind: QueryKind,
+        parse: impl FnOnce(Vec<u8>) -> Result<T>,
+    ) -> Result<Timed<T>>
+    where
+        A: ToSocketAddrs,
+    {
+        let addr = Self::resolve(addr).await?;
+        let _permit = self.limiter.acquire(addr.ip()).await;
+        let socket = UdpSocket::bind("0.0.0.0:0").await?;
+        let mut query = self.query(kind, addr);
+        let data = self.execute(&socket, addr, &mut query).await?;
+        Ok(Timed {
+            value: parse(data.value).map_err(|err| query.error(addr, err))?,
+            rtt: data.rtt,
+        })
+    }
 
-            for p in packets {
-                aggregation.extend(p.payload);
+    /// Runs `query` to completion, retrying as configured.
+    async fn execute(
+        &self,
+        socket: &UdpSocket,
+        addr: SocketAddr,
+        query: &mut Query,
+    ) -> Result<Timed<Vec<u8>>> {
+        let mut backoff = Backoff::new(self.retry);
+
+        loop {
+            let timeout = backoff.timeout(self.timeout);
+            match self.attempt(socket, addr, query, timeout).await {
+                Err(err) if retry::is_retriable(&err) && query.fallback() => {}
+                Err(err) => {
+                    time::sleep(backoff.retry(err)?).await;
+                    query.retry();
+                }
+                result => return result,
             }
+        }
+    }
 
-            if id as u32 & 0x80000000 != 0 {
-                let decompressed_size = read_buffer_offset!(&data, OFS_MP_SS_BZ2_SIZE, u32);
-                let checksum = read_buffer_offset!(&data, OFS_MP_SS_BZ2_CRC, u32);
+    /// Makes one attempt at `query`, giving up after `timeout`.
+    async fn attempt(
+        &self,
+        socket: &UdpSocket,
+        addr: SocketAddr,
+        query: &mut Query,
+        timeout: Duration,
+    ) -> Result<Timed<Vec<u8>>> {
+        self.send_to(socket, query.request(), addr)
+            .await
+            .map_err(|err| query.send_error(addr, err))?;
+        let mut sent = Instant::now();
+        let deadline = sent + timeout;
 
-                if decompressed_size > (1024 * 1024) {
-                    return Err(Error::InvalidBz2Size);
+        let mut data = vec![0; self.max_size];
+        let mut rtt = None;
+
+        loop {
+            data.resize(query.recv_size(), 0);
+            let read = self
+                .recv(socket, &mut data, deadline)
+                .await
+                .map_err(|err| query.recv_error(addr, err))?;
+            let elapsed = *rtt.get_or_insert_with(|| sent.elapsed());
+
+            match query
+                .push(&data[..read])
+                .map_err(|err| query.error(addr, err))?
+            {
+                QueryStatus::Pending => {}
+                QueryStatus::Resend => {
+                    if let Some(challenges) = &self.challenges {
+                        challenges.update(addr, query);
+                    }
+                    self.send_to(socket, query.request(), addr)
+                        .await
+                        .map_err(|err| query.send_error(addr, err))?;
+                    sent = Instant::now();
+                    rtt = None;
                 }
-
-                let mut decompressed = Vec::with_capacity(0);
-                decompressed.try_reserve(decompressed_size as usize)?;
-                decompressed.resize(decompressed_size as usize, 0);
-
-                BzDecoder::new(aggregation.deref()).read_exact(&mut decompressed)?;
-
-                if CRC32.checksum(&decompressed) != checksum {
-                    return Err(Error::CheckSumMismatch);
+                QueryStatus::Complete(value) => {
+                    return Ok(Timed {
+                        value,
+                        rtt: elapsed,
+                    })
                 }
-
-                Ok(decompressed)
-            } else {
-                Ok(aggregation)
             }
-        } else {
-            Err(Error::InvalidResponse)
         }
     }
+}
 
-    async fn do_challenge_request<A: ToSocketAddrs>(
-        &self,
-        addr: A,
-        header: &[u8],
-    ) -> Result<Vec<u8>> {
-        let packet = Vec::with_capacity(9);
-        let mut packet = Cursor::new(packet);
+impl A2SClientAsync {
+    pub async fn info<A: ToSocketAddrs>(&self, addr: A) -> Result<Info> {
+        self.info_timed(addr).await.map(|timed| timed.value)
+    }
 
-        packet.write_all(header)?;
-        packet.write_i32::<LittleEndian>(-1)?;
+    /// Queries A2S_INFO, along with the round-trip time.
+    pub async fn info_timed<A: ToSocketAddrs>(&self, addr: A) -> Result<Timed<Info>> {
+        self.do_challenge_request(addr, QueryKind::Info, |data| {
+            Info::from_cursor(Cursor::new(data))
+        })
+        .await
+    }
 
-        let data = self.send(packet.get_ref(), &addr).await?;
-        let mut data = Cursor::new(data);
+    /// Queries A2S_INFO, parsing the response leniently, see
+    /// [`Info::from_cursor_lenient`].
+    pub async fn info_lenient<A: ToSocketAddrs>(&self, addr: A) -> Result<Parsed<Info>> {
+        self.do_challenge_request(addr, QueryKind::Info, |data| {
+            Info::from_cursor_lenient(Cursor::new(data))
+        })
+        .await
+        .map(|timed| timed.value)
+    }
 
-        let header = data.read_u8()?;
-        if header != 'A' as u8 {
-            return Err(Error::InvalidResponse);
-        }
+    /// Queries A2S_INFO and accepts both the Source and the obsolete GoldSrc
+    /// response format.
+    pub async fn server_info<A: ToSocketAddrs>(&self, addr: A) -> Result<ServerInfo> {
+        self.do_challenge_request(addr, QueryKind::Info, |data| {
+            ServerInfo::from_cursor(Cursor::new(data))
+        })
+        .await
+        .map(|timed| timed.value)
+    }
 
-        let challenge = data.read_i32::<LittleEndian>()?;
+    pub async fn players<A: ToSocketAddrs>(&self, addr: A) -> Result<Vec<Player>> {
+        self.players_timed(addr).await.map(|timed| timed.value)
+    }
 
-        packet.set_position(5);
-        packet.write_i32::<LittleEndian>(challenge)?;
-        let data = self.send(packet.get_ref(), &addr).await?;
+    /// Queries A2S_PLAYER, along with the round-trip time.
+    pub async fn players_timed<A: ToSocketAddrs>(&self, addr: A) -> Result<Timed<Vec<Player>>> {
+        self.do_challenge_request(addr, QueryKind::Players, |data| {
+            Player::from_cursor(Cursor::new(data), self.app_id)
+        })
+        .await
+    }
 
-        Ok(data)
+    /// Queries A2S_PLAYER, along with the player count the response announced,
+    /// see [`Player::from_cursor_counted`].
+    pub async fn players_counted<A: ToSocketAddrs>(&self, addr: A) -> Result<Counted<Player>> {
+        self.do_challenge_request(addr, QueryKind::Players, |data| {
+            Player::from_cursor_counted(Cursor::new(data), self.app_id)
+        })
+        .await
+        .map(|timed| timed.value)
     }
-}
 
-impl A2SClientAsync {
-    pub async fn info<A: ToSocketAddrs>(&self, addr: A) -> Result<Info> {
-        let response = self.send(&INFO_REQUEST, &addr).await?;
+    /// Queries A2S_PLAYER, parsing the response leniently, see
+    /// [`Player::from_cursor_lenient`].
+    pub async fn players_lenient<A: ToSocketAddrs>(&self, addr: A) -> Result<Parsed<Vec<Player>>> {
+        self.do_challenge_request(addr, QueryKind::Players, |data| {
+            Player::from_cursor_lenient(Cursor::new(data), self.app_id)
+        })
+        .await
+        .map(|timed| timed.value)
+    }
 
-        let mut packet = Cursor::new(&response);
+    pub async fn rules<A: ToSocketAddrs>(&self, addr: A) -> Result<Vec<Rule>> {
+        self.rules_timed(addr).await.map(|timed| timed.value)
+    }
 
-        let header = packet.read_u8()?;
-        if header == b'A' {
-            let challenge = packet.read_i32::<LittleEndian>()?;
+    /// Queries A2S_RULES, along with the round-trip time.
+    pub async fn rules_timed<A: ToSocketAddrs>(&self, addr: A) -> Result<Timed<Vec<Rule>>> {
+        self.do_challenge_request(addr, QueryKind::Rules, |data| {
+            Rule::from_cursor(Cursor::new(data))
+        })
+        .await
+    }
 
-            let mut query = Vec::with_capacity(29);
-            query.write_all(&INFO_REQUEST)?;
-            query.write_i32::<LittleEndian>(challenge)?;
+    /// Queries A2S_RULES, along with the rule count the response announced,
+    /// see [`Rule::from_cursor_counted`].
+    pub async fn rules_counted<A: ToSocketAddrs>(&self, addr: A) -> Result<Counted<Rule>> {
+        self.do_challenge_request(addr, QueryKind::Rules, |data| {
+            Rule::from_cursor_counted(Cursor::new(data))
+        })
+        .await
+        .map(|timed| timed.value)
+    }
 
-            let data = self.send(&query, addr).await?;
-            Info::from_cursor(Cursor::new(data))
-        } else {
-            Info::from_cursor(Cursor::new(response))
-        }
+    /// Queries A2S_RULES, parsing the response leniently, see
+    /// [`Rule::from_cursor_lenient`].
+    pub async fn rules_lenient<A: ToSocketAddrs>(&self, addr: A) -> Result<Parsed<Vec<Rule>>> {
+        self.do_challenge_request(addr, QueryKind::Rules, |data| {
+            Rule::from_cursor_lenient(Cursor::new(data))
+        })
+        .await
+        .map(|timed| timed.value)
     }
 
-    pub async fn players<A: ToSocketAddrs>(&self, addr: A) -> Result<Vec<Player>> {
-        let data = self.do_challenge_request(addr, &PLAYER_REQUEST).await?;
-        Player::from_cursor(Cursor::new(data), self.app_id)
+    /// Queries A2S_RULES, keeping names and values as sent, see
+    /// [`RawRule::from_cursor`].
+    pub async fn rules_raw<A: ToSocketAddrs>(&self, addr: A) -> Result<Vec<RawRule>> {
+        self.do_challenge_request(addr, QueryKind::Rules, |data| {
+            RawRule::from_cursor(Cursor::new(data))
+        })
+        .await
+        .map(|timed| timed.value)
     }
 
-    pub async fn rules<A: ToSocketAddrs>(&self, addr: A) -> Result<Vec<Rule>> {
-        let data = self.do_challenge_request(addr, &RULES_REQUEST).await?;
-        Rule::from_cursor(Cursor::new(data))
+    /// Measures the round-trip time with A2A_PING.
+    ///
+    /// A2A_PING is deprecated and most Source servers don't answer it, see
+    /// [`A2SClientAsync::latency`].
+    pub async fn ping<A: ToSocketAddrs>(&self, addr: A) -> Result<Duration> {
+        self.do_challenge_request(addr, QueryKind::Ping, |data| {
+            read_header(&mut Cursor::new(data), PING_RESPONSE)
+        })
+        .await
+        .map(|timed| timed.rtt)
+    }
+
+    /// Measures the round-trip time with A2A_PING, or with A2S_INFO when the
+    /// server doesn't answer A2A_PING in time.
+    pub async fn latency<A: ToSocketAddrs>(&self, addr: A) -> Result<Duration> {
+        match self.ping(&addr).await {
+            Err(err) if retry::timed_out(&err) => Ok(self.info_timed(&addr).await?.rtt),
+            result => result,
+        }
     }
 }
@@ -0,0 +1,76 @@
+use crate::client_async::A2SClientAsync;
+use crate::errors::Result;
+use crate::query::QueryKind;
+use crate::types::snapshot;
+use crate::types::{Info, Player, Rule, ServerSnapshot, Timed};
+use std::io::Cursor;
+use std::net::SocketAddr;
+use std::time::SystemTime;
+use tokio::net::{ToSocketAddrs, UdpSocket};
+
+impl A2SClientAsync {
+    /// Queries A2S_INFO, A2S_PLAYER and A2S_RULES one after the other from a
+    /// single socket, reusing the challenge the server issued to the first
+    /// of them.
+    ///
+    /// Fails if A2S_INFO does, players and rules are left out of the
+    /// snapshot along with the error when their query fails.
+    pub async fn snapshot<A: ToSocketAddrs>(&self, addr: A) -> Result<ServerSnapshot> {
+        let addr = Self::resolve(addr).await?;
+        let _permit = self.limiter.acquire(addr.ip()).await;
+        let socket = UdpSocket::bind("0.0.0.0:0").await?;
+        let queried_at = SystemTime::now();
+        let mut challenge = None;
+
+        let info = self
+            .snapshot_query(&socket, addr, QueryKind::Info, &mut challenge, |data| {
+                Info::from_cursor(Cursor::new(data))
+            })
+            .await?;
+        let players = self
+            .snapshot_query(&socket, addr, QueryKind::Players, &mut challenge, |data| {
+                Player::from_cursor(Cursor::new(data), self.app_id)
+            })
+            .await;
+        let rules = self
+            .snapshot_query(&socket, addr, QueryKind::Rules, &mut challenge, |data| {
+                Rule::from_cursor(Cursor::new(data))
+            })
+            .await;
+
+        let (players, players_error) = snapshot::part(players);
+        let (rules, rules_error) = snapshot::part(rules);
+
+        Ok(ServerSnapshot {
+            info: info.value,
+            players,
+            players_error,
+            rules,
+            rules_error,
+            rtt: info.rtt,
+            queried_at,
+        })
+    }
+
+    async fn snapshot_query<T>(
+        &self,
+        socket: &UdpSocket,
+        addr: SocketAddr,
+        kind: QueryKind,
+        challenge: &mut Option<i32>,
+        parse: impl FnOnce(Vec<u8>) -> Result<T>,
+    ) -> Result<Timed<T>> {
+        let mut query = self.query(kind, addr);
+        if let Some(challenge) = *challenge {
+            query.use_challenge(challenge);
+        }
+
+        let result = self.execute(socket, addr, &mut query).await;
+        *challenge = query.challenge().or(*challenge);
+        let data = result?;
+        Ok(Timed {
+            value: parse(data.value).map_err(|err| query.error(addr, err))?,
+            rtt: data.rtt,
+        })
+    }
+}
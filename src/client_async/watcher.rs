@@ -0,0 +1,270 @@
+use crate::client_async::A2SClientAsync;
+use crate::types::diff::{self, FieldChange, InfoChange, PlayerChange, RuleChange};
+use crate::types::{ServerSnapshot, Text};
+use futures::future;
+use futures::stream::{self, Stream};
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::time::{self, MissedTickBehavior};
+
+/// Change observed on a watched server.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// The server answered, after not answering or on the first poll.
+    Online,
+
+    /// The server stopped answering, or didn't answer the first poll.
+    Offline,
+
+    MapChanged {
+        from: Text,
+        to: Text,
+    },
+
+    VersionChanged {
+        from: Text,
+        to: Text,
+    },
+
+    PlayerJoined {
+        name: Text,
+    },
+
+    /// `duration` is the time the player was connected as of the last poll.
+    PlayerLeft {
+        name: Text,
+        duration: f32,
+    },
+
+    /// The player count reached `threshold` if `rising`, or dropped below it.
+    PlayerThreshold {
+        threshold: u8,
+        players: u8,
+        rising: bool,
+    },
+
+    /// A rule was added (`from` is `None`), removed (`to` is `None`) or
+    /// changed value.
+    RuleChanged {
+        name: Text,
+        from: Option<Text>,
+        to: Option<Text>,
+    },
+}
+
+/// A [`Change`] observed on `server`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub server: SocketAddr,
+    pub change: Change,
+}
+
+#[derive(Debug)]
+enum State {
+    Unknown,
+    Online(Box<ServerSnapshot>),
+    Offline,
+}
+
+/// Watcher polls servers with [`A2SClientAsync::snapshot`] and reports what
+/// changed between polls.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use crowbar_a2s::Watcher;
+/// use futures::StreamExt;
+/// use std::time::Duration;
+///
+/// # async fn run() {
+/// let client = crowbar_a2s::Builder::new().build_async().unwrap();
+/// let mut watcher = Watcher::new(client);
+/// watcher
+///     .interval(Duration::from_secs(60))
+///     .server("127.0.0.1:27015".parse().unwrap())
+///     .player_threshold(10);
+///
+/// let events = std::pin::pin!(watcher.watch());
+/// events
+///     .for_each(|event| async move { println!("{:?}", event) })
+///     .await;
+/// # }
+/// ```
+pub struct Watcher {
+    client: A2SClientAsync,
+    interval: Duration,
+    thresholds: Vec<u8>,
+    servers: Vec<(SocketAddr, State)>,
+}
+
+impl Watcher {
+    pub fn new(client: A2SClientAsync) -> Self {
+        Self {
+            client,
+            interval: Duration::from_secs(30),
+            thresholds: Vec::new(),
+            servers: Vec::new(),
+        }
+    }
+
+    /// Time between polls of [`Watcher::watch`]. Defaults to 30 seconds.
+    pub fn interval(&mut self, interval: Duration) -> &mut Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Adds a server to watch.
+    pub fn server(&mut self, addr: SocketAddr) -> &mut Self {
+        if self.servers.iter().all(|(server, _)| *server != addr) {
+            self.servers.push((addr, State::Unknown));
+        }
+        self
+    }
+
+    /// Reports [`Change::PlayerThreshold`] when the player count of a server
+    /// crosses `threshold`.
+    pub fn player_threshold(&mut self, threshold: u8) -> &mut Self {
+        self.thresholds.push(threshold);
+        self
+    }
+
+    /// Snapshot of `server` as of the last poll, if it answered.
+    pub fn snapshot(&self, server: SocketAddr) -> Option<&ServerSnapshot> {
+        self.servers.iter().find_map(|(addr, state)| match state {
+            State::Online(snapshot) if *addr == server => Some(&**snapshot),
+            _ => None,
+        })
+    }
+
+    /// Polls every server once, concurrently, returning what changed since
+    /// the last poll.
+    pub async fn poll(&mut self) -> Vec<Event> {
+        let client = &self.client;
+        let snapshots =
+            future::join_all(self.servers.iter().map(|(addr, _)| client.snapshot(*addr))).await;
+
+        let mut events = Vec::new();
+        for ((server, state), snapshot) in self.servers.iter_mut().zip(snapshots) {
+            let next = match snapshot {
+                Ok(snapshot) => State::Online(Box::new(snapshot)),
+                Err(_) => State::Offline,
+            };
+
+            let changes = changes(&self.thresholds, state, &next);
+            events.extend(changes.into_iter().map(|change| Event {
+                server: *server,
+                change,
+            }));
+            *state = next;
+        }
+
+        events
+    }
+
+    /// Polls the servers every interval, yielding the changes.
+    ///
+    /// The first poll is immediate. Polls are delayed rather than skipped
+    /// when servers are slow to answer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the interval is zero.
+    pub fn watch(self) -> impl Stream<Item = Event> {
+        let mut ticker = time::interval(self.interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        stream::unfold(
+            (self, ticker, VecDeque::new()),
+            |(mut watcher, mut ticker, mut pending)| async move {
+                loop {
+                    if let Some(event) = pending.pop_front() {
+                        return Some((event, (watcher, ticker, pending)));
+                    }
+                    ticker.tick().await;
+                    pending.extend(watcher.poll().await);
+                }
+            },
+        )
+    }
+}
+
+fn changes(thresholds: &[u8], previous: &State, next: &State) -> Vec<Change> {
+    let (previous, next) = match (previous, next) {
+        (State::Online(previous), State::Online(next)) => (previous, next),
+        (State::Offline, State::Offline) | (_, State::Unknown) => return Vec::new(),
+        (_, State::Online(_)) => return vec![Change::Online],
+        (_, State::Offline) => return vec![Change::Offline],
+    };
+
+    let mut changes = Vec::new();
+
+    for change in diff::info(&previous.info, &next.info) {
+        match change {
+            InfoChange::Map(FieldChange { from, to }) => {
+                changes.push(Change::MapChanged { from, to })
+            }
+            InfoChange::Version(FieldChange { from, to }) => {
+                changes.push(Change::VersionChanged { from, to })
+            }
+            _ => {}
+        }
+    }
+
+    if let (Some(previous), Some(next)) = (&previous.players, &next.players) {
+        changes.extend(
+            diff::players(previous, next)
+                .into_iter()
+                .map(|change| match change {
+                    PlayerChange::Joined(player) => Change::PlayerJoined { name: player.name },
+                    PlayerChange::Left(player) => Change::PlayerLeft {
+                        name: player.name,
+                        duration: player.duration,
+                    },
+                }),
+        );
+    }
+
+    let (from, to) = (previous.info.players, next.info.players);
+    for &threshold in thresholds {
+        if from < threshold && to >= threshold {
+            changes.push(Change::PlayerThreshold {
+                threshold,
+                players: to,
+                rising: true,
+            });
+        } else if from >= threshold && to < threshold {
+            changes.push(Change::PlayerThreshold {
+                threshold,
+                players: to,
+                rising: false,
+            });
+        }
+    }
+
+    if let (Some(previous), Some(next)) = (&previous.rules, &next.rules) {
+        changes.extend(
+            diff::rules(previous, next)
+                .into_iter()
+                .map(|change| match change {
+                    RuleChange::Added(rule) => Change::RuleChanged {
+                        name: rule.name,
+                        from: None,
+                        to: Some(rule.value),
+                    },
+                    RuleChange::Removed(rule) => Change::RuleChanged {
+                        name: rule.name,
+                        from: Some(rule.value),
+                        to: None,
+                    },
+                    RuleChange::Modified { name, value } => Change::RuleChanged {
+                        name,
+                        from: Some(value.from),
+                        to: Some(value.to),
+                    },
+                }),
+        );
+    }
+
+    changes
+}
@@ -12,6 +12,8 @@ pub const OFS_MP_SS_BZ2_SIZE: usize = 12;
 pub const OFS_MP_SS_BZ2_CRC: usize = 16;
 pub const OFS_MP_SS_PAYLOAD: usize = OFS_MP_SS_BZ2_SIZE;
 pub const OFS_MP_SS_PAYLOAD_BZ2: usize = OFS_MP_SS_BZ2_CRC + 4;
+pub const OFS_MP_GS_PACKET: usize = 8;
+pub const OFS_MP_GS_PAYLOAD: usize = 9;
 
 #[derive(Debug)]
 pub struct PacketFragment {
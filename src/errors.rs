@@ -1,7 +1,32 @@
+use crate::query::QueryKind;
+use std::fmt;
+use std::net::SocketAddr;
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// Whether an IO error of a UDP socket only concerns one datagram or peer,
+/// rather than the socket. Windows reports ICMP port unreachable messages
+/// as `ConnectionReset` on a later `recv_from`, and sending to spoofed
+/// addresses fails with `InvalidInput` or `PermissionDenied`.
+#[cfg(any(feature = "sync", feature = "async"))]
+pub(crate) fn is_transient(err: &std::io::Error) -> bool {
+    use std::io::ErrorKind::*;
+
+    matches!(
+        err.kind(),
+        ConnectionReset
+            | ConnectionRefused
+            | ConnectionAborted
+            | InvalidInput
+            | PermissionDenied
+            | AddrNotAvailable
+            | HostUnreachable
+            | NetworkUnreachable
+            | Interrupted
+    )
+}
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("IO error {0}")]
@@ -16,6 +41,19 @@ pub enum Error {
     #[error("Invalid response")]
     InvalidResponse,
 
+    #[error("Unexpected header 0x{found:02X}, expected 0x{expected:02X}")]
+    UnexpectedHeader { expected: u8, found: u8 },
+
+    #[error("Invalid {field}: {source}")]
+    InvalidField {
+        field: &'static str,
+        #[source]
+        source: Box<Error>,
+    },
+
+    #[error("Missing rules chunk {index} of {count}")]
+    MissingChunk { index: u8, count: u8 },
+
     #[error("Mismatch packet ID")]
     MismatchID,
 
@@ -25,6 +63,68 @@ pub enum Error {
     #[error("Decompressed checksum does not match")]
     CheckSumMismatch,
 
+    #[error("Gave up after {attempts} attempts: {source}")]
+    RetriesExhausted {
+        attempts: u32,
+        #[source]
+        source: Box<Error>,
+    },
+
+    /// A query to a server failed, for a response which couldn't be
+    /// reassembled or parsed, or for a timeout or socket error while sending
+    /// the request or waiting for a datagram.
+    #[error(transparent)]
+    Query(Box<QueryError>),
+
+    #[error("RCON authentication failed")]
+    RconAuth,
+
     #[error("{0}")]
     Other(&'static str),
 }
+
+/// Where a query to a server failed, and what it received.
+#[derive(Debug, Error)]
+#[error("{kind:?} query to {server} failed at {stage}: {source}")]
+pub struct QueryError {
+    pub server: SocketAddr,
+    pub kind: QueryKind,
+    pub stage: Stage,
+    /// Datagrams received for the failed response, if enabled with
+    /// [`Builder::capture_datagrams`](crate::Builder::capture_datagrams)
+    pub datagrams: Vec<Vec<u8>>,
+    #[source]
+    pub source: Error,
+}
+
+/// Step of a query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Sending the request.
+    Request,
+
+    /// Handling a challenge issued by the server.
+    Challenge,
+
+    /// Receiving and reassembling the response, at the n-th datagram for
+    /// it, counting from 0.
+    Fragment(usize),
+
+    /// Decompressing a bzip2 compressed response.
+    Decompression,
+
+    /// Parsing the complete response.
+    Parse,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Request => write!(f, "request"),
+            Self::Challenge => write!(f, "challenge"),
+            Self::Fragment(n) => write!(f, "fragment {}", n),
+            Self::Decompression => write!(f, "decompression"),
+            Self::Parse => write!(f, "parse"),
+        }
+    }
+}
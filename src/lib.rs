@@ -1,21 +1,49 @@
 #[cfg(feature = "sync")]
 pub mod client;
 #[cfg(feature = "async")]
+#[macro_use]
 pub mod client_async;
 pub mod constants;
 pub mod errors;
+pub mod master;
+pub mod packet;
+pub mod query;
+#[cfg(feature = "rcon")]
+pub mod rcon;
+pub mod retry;
+pub mod server;
 pub mod types;
 
 #[cfg(feature = "sync")]
 pub use crate::client::A2SClient;
 #[cfg(feature = "async")]
+pub use crate::client_async::exporter::Exporter;
+#[cfg(feature = "async")]
+pub use crate::client_async::watcher::Watcher;
+#[cfg(feature = "async")]
 pub use crate::client_async::A2SClientAsync;
+#[cfg(any(feature = "sync", feature = "async"))]
 use crate::errors::Result;
+pub use crate::packet::{Engine, Reassembler};
+#[cfg(any(feature = "sync", feature = "async"))]
+use crate::query::ChallengeCache;
+use crate::query::ChallengeMode;
+#[cfg(all(feature = "rcon", feature = "sync"))]
+pub use crate::rcon::RconClient;
+#[cfg(all(feature = "rcon", feature = "async"))]
+pub use crate::rcon::RconClientAsync;
+pub use crate::retry::RetryPolicy;
 use crc::Crc;
+#[cfg(feature = "encoding")]
+pub use encoding_rs;
 use std::time::Duration;
 
+#[cfg(feature = "async")]
+use crate::client_async::limit::{Limiter, Limits};
 #[cfg(feature = "sync")]
 use std::net::UdpSocket;
+#[cfg(feature = "async")]
+use std::sync::Arc;
 
 pub const CRC32: crc::Crc<u32> = Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
 
@@ -23,6 +51,20 @@ pub struct Builder {
     max_size: usize,
     app_id: u16,
     timeout: Duration,
+    fragment_timeout: Option<Duration>,
+    challenge_ttl: Option<Duration>,
+    challenge_mode: ChallengeMode,
+    engine: Engine,
+    retry: RetryPolicy,
+    capture: bool,
+    #[cfg(feature = "async")]
+    limits: Limits,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Builder {
@@ -31,6 +73,14 @@ impl Builder {
             max_size: 1400,
             app_id: 0,
             timeout: Duration::new(5, 0),
+            fragment_timeout: None,
+            challenge_ttl: None,
+            challenge_mode: ChallengeMode::Request,
+            engine: Engine::Source,
+            retry: RetryPolicy::default(),
+            capture: false,
+            #[cfg(feature = "async")]
+            limits: Limits::default(),
         }
     }
 
@@ -44,11 +94,84 @@ impl Builder {
         self
     }
 
+    /// Total time a query may take, from sending the request to receiving
+    /// the last fragment of the response, challenge round-trip included.
+    /// Each retry gets a fresh one. Defaults to 5 seconds.
     pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
         self.timeout = timeout;
         self
     }
 
+    /// Time a query may wait for the next datagram, so servers which stop
+    /// answering halfway through a response fail early. Unset by default,
+    /// leaving only [`Builder::timeout`].
+    pub fn fragment_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.fragment_timeout = Some(timeout);
+        self
+    }
+
+    /// Engine generation of the servers to query, which decides how split
+    /// responses are reassembled. Defaults to [`Engine::Source`].
+    pub fn engine(&mut self, engine: Engine) -> &mut Self {
+        self.engine = engine;
+        self
+    }
+
+    /// Caches the challenges servers issue for `ttl`, so queries to a server
+    /// queried recently skip requesting one. Disabled by default.
+    pub fn challenge_cache(&mut self, ttl: Duration) -> &mut Self {
+        self.challenge_ttl = Some(ttl);
+        self
+    }
+
+    /// How A2S_PLAYER and A2S_RULES queries ask for a challenge. Defaults to
+    /// [`ChallengeMode::Request`], use [`ChallengeMode::Legacy`] or
+    /// [`ChallengeMode::Fallback`] for servers only answering the deprecated
+    /// A2S_SERVERQUERY_GETCHALLENGE request.
+    pub fn challenge_mode(&mut self, mode: ChallengeMode) -> &mut Self {
+        self.challenge_mode = mode;
+        self
+    }
+
+    /// How queries are retried when datagrams get lost. Defaults to a single
+    /// attempt.
+    pub fn retry(&mut self, policy: RetryPolicy) -> &mut Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Keeps the datagrams of responses which failed to reassemble or parse
+    /// in the [`QueryError`](crate::errors::QueryError) returned, to triage
+    /// misbehaving servers. Disabled by default.
+    pub fn capture_datagrams(&mut self, capture: bool) -> &mut Self {
+        self.capture = capture;
+        self
+    }
+
+    /// Maximum number of queries the async client has in flight at once,
+    /// across every call sharing the client. Unlimited by default.
+    #[cfg(feature = "async")]
+    pub fn max_in_flight(&mut self, max: usize) -> &mut Self {
+        self.limits.max_in_flight = Some(max);
+        self
+    }
+
+    /// Maximum number of packets the async client sends per second, with
+    /// bursts of up to one second worth of packets. Unlimited by default.
+    #[cfg(feature = "async")]
+    pub fn packets_per_second(&mut self, rate: u32) -> &mut Self {
+        self.limits.packets_per_second = Some(rate);
+        self
+    }
+
+    /// Maximum number of queries the async client has in flight to a single
+    /// IP address, whatever the port. Unlimited by default.
+    #[cfg(feature = "async")]
+    pub fn per_host_limit(&mut self, max: usize) -> &mut Self {
+        self.limits.per_host = Some(max);
+        self
+    }
+
     #[cfg(feature = "sync")]
     pub fn build_sync(&self) -> Result<A2SClient> {
         let socket = UdpSocket::bind("0.0.0.0:0")?;
@@ -61,6 +184,13 @@ impl Builder {
             socket,
             max_size: self.max_size,
             app_id: self.app_id,
+            engine: self.engine,
+            retry: self.retry,
+            timeout,
+            fragment_timeout: self.fragment_timeout,
+            challenges: self.challenge_ttl.map(ChallengeCache::new),
+            challenge_mode: self.challenge_mode,
+            capture: self.capture,
         })
     }
 
@@ -68,8 +198,37 @@ impl Builder {
     pub fn build_async(&self) -> Result<A2SClientAsync> {
         Ok(A2SClientAsync {
             timeout: self.timeout,
+            fragment_timeout: self.fragment_timeout,
             max_size: self.max_size,
             app_id: self.app_id,
+            engine: self.engine,
+            retry: self.retry,
+            limiter: Arc::new(Limiter::new(self.limits)),
+            challenges: self
+                .challenge_ttl
+                .map(|ttl| Arc::new(ChallengeCache::new(ttl))),
+            challenge_mode: self.challenge_mode,
+            capture: self.capture,
         })
     }
+
+    /// Connects and authenticates to the Source RCON server at `addr`.
+    #[cfg(all(feature = "rcon", feature = "sync"))]
+    pub fn connect_rcon<A: std::net::ToSocketAddrs>(
+        &self,
+        addr: A,
+        password: &str,
+    ) -> Result<RconClient> {
+        RconClient::connect(addr, password, self.timeout)
+    }
+
+    /// Connects and authenticates to the Source RCON server at `addr`.
+    #[cfg(all(feature = "rcon", feature = "async"))]
+    pub async fn connect_rcon_async<A: tokio::net::ToSocketAddrs>(
+        &self,
+        addr: A,
+        password: &str,
+    ) -> Result<RconClientAsync> {
+        RconClientAsync::connect(addr, password, self.timeout).await
+    }
 }
@@ -0,0 +1,379 @@
+use crate::errors::{Error, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// A single master server filter condition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    /// `\dedicated\1`: servers running dedicated
+    Dedicated(bool),
+
+    /// `\secure\1`: servers using anti-cheat technology (VAC, but potentially others as well)
+    Secure(bool),
+
+    /// `\gamedir\[mod]`: servers running the specified modification (ex. cstrike)
+    GameDir(String),
+
+    /// `\map\[map]`: servers running the specified map (ex. cs_italy)
+    Map(String),
+
+    /// `\linux\1`: servers running on a Linux platform
+    Linux(bool),
+
+    /// `\password\0`: servers that are not password protected
+    Password(bool),
+
+    /// `\empty\1`: servers that are not empty
+    Empty(bool),
+
+    /// `\full\1`: servers that are not full
+    Full(bool),
+
+    /// `\proxy\1`: servers that are spectator proxies
+    Proxy(bool),
+
+    /// `\appid\[appid]`: servers that are running game \[appid\]
+    AppId(u32),
+
+    /// `\napp\[appid]`: servers that are NOT running game \[appid\]
+    NotAppId(u32),
+
+    /// `\noplayers\1`: servers that are empty
+    NoPlayers(bool),
+
+    /// `\white\1`: servers that are whitelisted
+    White(bool),
+
+    /// `\gametype\[tag,...]`: servers with all of the given tag(s) in sv_tags
+    GameType(Vec<String>),
+
+    /// `\gamedata\[tag,...]`: servers with all of the given tag(s) in their 'hidden' tags (L4D2)
+    GameData(Vec<String>),
+
+    /// `\gamedataor\[tag,...]`: servers with any of the given tag(s) in their 'hidden' tags (L4D2)
+    GameDataOr(Vec<String>),
+
+    /// `\name_match\[hostname]`: servers with their hostname matching \[hostname\] (can use * as a wildcard)
+    NameMatch(String),
+
+    /// `\version_match\[version]`: servers running version \[version\] (can use * as a wildcard)
+    VersionMatch(String),
+
+    /// `\collapse_addr_hash\1`: return only one server for each unique IP address matched
+    CollapseAddrHash(bool),
+
+    /// `\gameaddr\[ip]`: return only servers on the specified IP address (port supported and optional)
+    GameAddr(String),
+
+    /// `\nor\[x]`: a special filter, specifies that servers matching any of the following \[x\] conditions should not be returned
+    Nor(Vec<Condition>),
+
+    /// `\nand\[x]`: a special filter, specifies that servers matching all of the following \[x\] conditions should not be returned
+    Nand(Vec<Condition>),
+
+    /// Any filter not known to this crate, kept as key and value
+    Other(String, String),
+}
+
+/// Filter is a builder for the filter string of a master server query.
+///
+/// It renders to the wire format through [`fmt::Display`] and can be parsed
+/// back from it through [`FromStr`].
+///
+/// # Example
+///
+/// ```rust
+/// use crowbar_a2s::master::Filter;
+///
+/// let filter = Filter::new()
+///     .appid(240)
+///     .map("de_dust2")
+///     .nor(Filter::new().empty(false).password(true))
+///     .to_string();
+///
+/// assert_eq!(filter, "\\appid\\240\\map\\de_dust2\\nor\\2\\empty\\0\\password\\1");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filter {
+    conditions: Vec<Condition>,
+}
+
+fn tags<I, S>(tags: I) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    tags.into_iter().map(|t| t.as_ref().to_string()).collect()
+}
+
+impl Filter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn conditions(&self) -> &[Condition] {
+        &self.conditions
+    }
+
+    pub fn condition(&mut self, condition: Condition) -> &mut Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    pub fn dedicated(&mut self, dedicated: bool) -> &mut Self {
+        self.condition(Condition::Dedicated(dedicated))
+    }
+
+    pub fn secure(&mut self, secure: bool) -> &mut Self {
+        self.condition(Condition::Secure(secure))
+    }
+
+    pub fn gamedir(&mut self, gamedir: &str) -> &mut Self {
+        self.condition(Condition::GameDir(gamedir.to_string()))
+    }
+
+    pub fn map(&mut self, map: &str) -> &mut Self {
+        self.condition(Condition::Map(map.to_string()))
+    }
+
+    pub fn linux(&mut self, linux: bool) -> &mut Self {
+        self.condition(Condition::Linux(linux))
+    }
+
+    pub fn password(&mut self, password: bool) -> &mut Self {
+        self.condition(Condition::Password(password))
+    }
+
+    /// `true` matches servers that are not empty.
+    pub fn empty(&mut self, empty: bool) -> &mut Self {
+        self.condition(Condition::Empty(empty))
+    }
+
+    /// `true` matches servers that are not full.
+    pub fn full(&mut self, full: bool) -> &mut Self {
+        self.condition(Condition::Full(full))
+    }
+
+    pub fn proxy(&mut self, proxy: bool) -> &mut Self {
+        self.condition(Condition::Proxy(proxy))
+    }
+
+    pub fn appid(&mut self, app_id: u32) -> &mut Self {
+        self.condition(Condition::AppId(app_id))
+    }
+
+    pub fn napp(&mut self, app_id: u32) -> &mut Self {
+        self.condition(Condition::NotAppId(app_id))
+    }
+
+    /// `true` matches servers that are empty.
+    pub fn noplayers(&mut self, noplayers: bool) -> &mut Self {
+        self.condition(Condition::NoPlayers(noplayers))
+    }
+
+    pub fn white(&mut self, white: bool) -> &mut Self {
+        self.condition(Condition::White(white))
+    }
+
+    pub fn gametype<I, S>(&mut self, tags: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.condition(Condition::GameType(self::tags(tags)))
+    }
+
+    pub fn gamedata<I, S>(&mut self, tags: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.condition(Condition::GameData(self::tags(tags)))
+    }
+
+    pub fn gamedataor<I, S>(&mut self, tags: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.condition(Condition::GameDataOr(self::tags(tags)))
+    }
+
+    pub fn name_match(&mut self, hostname: &str) -> &mut Self {
+        self.condition(Condition::NameMatch(hostname.to_string()))
+    }
+
+    pub fn version_match(&mut self, version: &str) -> &mut Self {
+        self.condition(Condition::VersionMatch(version.to_string()))
+    }
+
+    pub fn collapse_addr_hash(&mut self, collapse: bool) -> &mut Self {
+        self.condition(Condition::CollapseAddrHash(collapse))
+    }
+
+    pub fn gameaddr(&mut self, addr: &str) -> &mut Self {
+        self.condition(Condition::GameAddr(addr.to_string()))
+    }
+
+    /// Excludes servers matching any of the conditions in `group`.
+    pub fn nor(&mut self, group: &Filter) -> &mut Self {
+        self.condition(Condition::Nor(group.conditions.clone()))
+    }
+
+    /// Excludes servers matching all of the conditions in `group`.
+    pub fn nand(&mut self, group: &Filter) -> &mut Self {
+        self.condition(Condition::Nand(group.conditions.clone()))
+    }
+}
+
+/// Value written without backslashes, which would end it and let the rest
+/// through as conditions of its own.
+struct Value<'a>(&'a str);
+
+impl fmt::Display for Value<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.split('\\').try_for_each(|part| f.write_str(part))
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flag = |value: &bool| if *value { "1" } else { "0" };
+
+        match self {
+            Self::Dedicated(v) => write!(f, "\\dedicated\\{}", flag(v)),
+            Self::Secure(v) => write!(f, "\\secure\\{}", flag(v)),
+            Self::GameDir(v) => write!(f, "\\gamedir\\{}", Value(v)),
+            Self::Map(v) => write!(f, "\\map\\{}", Value(v)),
+            Self::Linux(v) => write!(f, "\\linux\\{}", flag(v)),
+            Self::Password(v) => write!(f, "\\password\\{}", flag(v)),
+            Self::Empty(v) => write!(f, "\\empty\\{}", flag(v)),
+            Self::Full(v) => write!(f, "\\full\\{}", flag(v)),
+            Self::Proxy(v) => write!(f, "\\proxy\\{}", flag(v)),
+            Self::AppId(v) => write!(f, "\\appid\\{}", v),
+            Self::NotAppId(v) => write!(f, "\\napp\\{}", v),
+            Self::NoPlayers(v) => write!(f, "\\noplayers\\{}", flag(v)),
+            Self::White(v) => write!(f, "\\white\\{}", flag(v)),
+            Self::GameType(v) => write!(f, "\\gametype\\{}", Value(&v.join(","))),
+            Self::GameData(v) => write!(f, "\\gamedata\\{}", Value(&v.join(","))),
+            Self::GameDataOr(v) => write!(f, "\\gamedataor\\{}", Value(&v.join(","))),
+            Self::NameMatch(v) => write!(f, "\\name_match\\{}", Value(v)),
+            Self::VersionMatch(v) => write!(f, "\\version_match\\{}", Value(v)),
+            Self::CollapseAddrHash(v) => write!(f, "\\collapse_addr_hash\\{}", flag(v)),
+            Self::GameAddr(v) => write!(f, "\\gameaddr\\{}", Value(v)),
+            Self::Nor(group) | Self::Nand(group) => {
+                let key = if matches!(self, Self::Nor(_)) {
+                    "nor"
+                } else {
+                    "nand"
+                };
+                write!(f, "\\{}\\{}", key, group.len())?;
+                group.iter().try_for_each(|c| c.fmt(f))
+            }
+            Self::Other(key, value) => write!(f, "\\{}\\{}", Value(key), Value(value)),
+        }
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.conditions.iter().try_for_each(|c| c.fmt(f))
+    }
+}
+
+fn parse_flag(value: &str) -> Result<bool> {
+    match value {
+        "1" => Ok(true),
+        "0" => Ok(false),
+        _ => Err(Error::Other("Invalid filter flag")),
+    }
+}
+
+fn parse_number(value: &str) -> Result<u32> {
+    value
+        .parse()
+        .map_err(|_| Error::Other("Invalid filter number"))
+}
+
+fn parse_tags(value: &str) -> Vec<String> {
+    value.split(',').map(str::to_string).collect()
+}
+
+/// Parses the next condition, consuming following conditions for groups.
+fn parse_condition<'a, I>(pairs: &mut I) -> Result<Option<Condition>>
+where
+    I: Iterator<Item = (&'a str, &'a str)>,
+{
+    let (key, value) = match pairs.next() {
+        Some(pair) => pair,
+        None => return Ok(None),
+    };
+
+    let condition = match key {
+        "dedicated" => Condition::Dedicated(parse_flag(value)?),
+        "secure" => Condition::Secure(parse_flag(value)?),
+        "gamedir" => Condition::GameDir(value.to_string()),
+        "map" => Condition::Map(value.to_string()),
+        "linux" => Condition::Linux(parse_flag(value)?),
+        "password" => Condition::Password(parse_flag(value)?),
+        "empty" => Condition::Empty(parse_flag(value)?),
+        "full" => Condition::Full(parse_flag(value)?),
+        "proxy" => Condition::Proxy(parse_flag(value)?),
+        "appid" => Condition::AppId(parse_number(value)?),
+        "napp" => Condition::NotAppId(parse_number(value)?),
+        "noplayers" => Condition::NoPlayers(parse_flag(value)?),
+        "white" => Condition::White(parse_flag(value)?),
+        "gametype" => Condition::GameType(parse_tags(value)),
+        "gamedata" => Condition::GameData(parse_tags(value)),
+        "gamedataor" => Condition::GameDataOr(parse_tags(value)),
+        "name_match" => Condition::NameMatch(value.to_string()),
+        "version_match" => Condition::VersionMatch(value.to_string()),
+        "collapse_addr_hash" => Condition::CollapseAddrHash(parse_flag(value)?),
+        "gameaddr" => Condition::GameAddr(value.to_string()),
+        "nor" | "nand" => {
+            let count = parse_number(value)?;
+            let mut group = Vec::new();
+            for _ in 0..count {
+                group.push(
+                    parse_condition(pairs)?
+                        .ok_or(Error::Other("Missing conditions in filter group"))?,
+                );
+            }
+            if key == "nor" {
+                Condition::Nor(group)
+            } else {
+                Condition::Nand(group)
+            }
+        }
+        _ => Condition::Other(key.to_string(), value.to_string()),
+    };
+
+    Ok(Some(condition))
+}
+
+impl FromStr for Filter {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.is_empty() {
+            return Ok(Self::new());
+        }
+
+        let fields: Vec<&str> = s
+            .strip_prefix('\\')
+            .ok_or(Error::Other("Filter must start with a backslash"))?
+            .split('\\')
+            .collect();
+        if !fields.len().is_multiple_of(2) {
+            return Err(Error::Other("Filter key without value"));
+        }
+
+        let mut pairs = fields.chunks_exact(2).map(|pair| (pair[0], pair[1]));
+        let mut filter = Self::new();
+        while let Some(condition) = parse_condition(&mut pairs)? {
+            filter.conditions.push(condition);
+        }
+
+        Ok(filter)
+    }
+}
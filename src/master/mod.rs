@@ -0,0 +1,142 @@
+//! Valve master server query protocol.
+//!
+//! Master servers answer a `0x31` request carrying a region, a seed address
+//! and a filter string with a page of server addresses. The next page is
+//! requested with the last address received as the seed, until the master
+//! server terminates the list with `0.0.0.0:0`.
+//!
+//! [`MasterQuery`] implements the pagination without touching a socket; the
+//! sync and async clients drive it through `master_servers`. The filter
+//! string is built with [`Filter`].
+
+pub mod filter;
+
+pub use crate::master::filter::{Condition, Filter};
+
+use crate::errors::{Error, Result};
+use std::collections::VecDeque;
+use std::net::{Ipv4Addr, SocketAddrV4};
+
+/// Master server for Source engine games.
+pub const MASTER_SOURCE: &str = "hl2master.steampowered.com:27011";
+
+/// Master server for GoldSrc engine games.
+pub const MASTER_GOLDSRC: &str = "hl1master.steampowered.com:27010";
+
+pub const MASTER_REQUEST_HEADER: u8 = 0x31;
+
+pub const MASTER_RESPONSE_HEADER: [u8; 6] = [0xFF, 0xFF, 0xFF, 0xFF, 0x66, 0x0A];
+
+/// First and last address of the server list.
+pub const MASTER_SEED: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Region {
+    USEastCoast = 0x00,
+    USWestCoast = 0x01,
+    SouthAmerica = 0x02,
+    Europe = 0x03,
+    Asia = 0x04,
+    Australia = 0x05,
+    MiddleEast = 0x06,
+    Africa = 0x07,
+    RestOfWorld = 0xFF,
+}
+
+/// MasterQuery is a sans-IO state machine paginating through a master
+/// server list.
+///
+/// Send the datagram returned by [`MasterQuery::next_request`], feed the
+/// response into [`MasterQuery::push_response`] and drain addresses with
+/// [`MasterQuery::pop`], until `next_request` returns `None`.
+#[derive(Debug)]
+pub struct MasterQuery {
+    region: Region,
+    filter: String,
+    seed: SocketAddrV4,
+    done: bool,
+    addresses: VecDeque<SocketAddrV4>,
+}
+
+impl MasterQuery {
+    pub fn new(region: Region, filter: &Filter) -> Self {
+        Self {
+            region,
+            filter: filter.to_string(),
+            seed: MASTER_SEED,
+            done: false,
+            addresses: VecDeque::new(),
+        }
+    }
+
+    /// Whether the end of the list has been received.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Request for the next page, or `None` if the list is complete.
+    pub fn next_request(&self) -> Option<Vec<u8>> {
+        if self.done {
+            return None;
+        }
+
+        let seed = self.seed.to_string();
+        let mut request = Vec::with_capacity(4 + seed.len() + self.filter.len());
+        request.push(MASTER_REQUEST_HEADER);
+        request.push(self.region as u8);
+        request.extend(seed.as_bytes());
+        request.push(0);
+        request.extend(self.filter.as_bytes());
+        request.push(0);
+
+        Some(request)
+    }
+
+    /// Feeds one response datagram from the master server.
+    pub fn push_response(&mut self, data: &[u8]) -> Result<()> {
+        if data.len() < MASTER_RESPONSE_HEADER.len()
+            || data[..MASTER_RESPONSE_HEADER.len()] != MASTER_RESPONSE_HEADER
+        {
+            return Err(Error::InvalidResponse);
+        }
+
+        let entries = data[MASTER_RESPONSE_HEADER.len()..].chunks_exact(6);
+        if !entries.remainder().is_empty() {
+            return Err(Error::InvalidResponse);
+        }
+
+        let mut received = false;
+        for entry in entries {
+            let addr = SocketAddrV4::new(
+                Ipv4Addr::new(entry[0], entry[1], entry[2], entry[3]),
+                u16::from_be_bytes([entry[4], entry[5]]),
+            );
+
+            if addr == MASTER_SEED {
+                self.done = true;
+                break;
+            }
+
+            // Pages may start with the seed they were requested with
+            if addr == self.seed {
+                continue;
+            }
+
+            self.seed = addr;
+            self.addresses.push_back(addr);
+            received = true;
+        }
+
+        if !received {
+            self.done = true;
+        }
+
+        Ok(())
+    }
+
+    /// Takes the next received address.
+    pub fn pop(&mut self) -> Option<SocketAddrV4> {
+        self.addresses.pop_front()
+    }
+}
@@ -0,0 +1,176 @@
+use crate::constants::*;
+use crate::errors::{Error, Result};
+use crate::packet::Engine;
+use crate::CRC32;
+use bzip2::write::BzEncoder;
+use bzip2::Compression;
+use std::io::Write;
+
+/// Encoder is the counterpart of [`Reassembler`](crate::Reassembler),
+/// splitting a response payload into datagrams of at most `max_size` bytes.
+///
+/// Payloads are passed with their `0xFFFFFFFF` header, as returned by e.g.
+/// [`Info::to_bytes`](crate::types::Info::to_bytes). Payloads fitting into a
+/// single datagram are sent as is.
+///
+/// # Example
+///
+/// ```rust
+/// use crowbar_a2s::packet::Encoder;
+/// use crowbar_a2s::Reassembler;
+///
+/// let mut payload = vec![0xFF, 0xFF, 0xFF, 0xFF, 0x45];
+/// payload.resize(4000, b'x');
+///
+/// let datagrams = Encoder::new(1400).compress(true).encode(1, &payload).unwrap();
+///
+/// let mut reassembler = Reassembler::new(1400);
+/// let mut result = None;
+/// for datagram in datagrams {
+///     result = reassembler.push(&datagram).unwrap();
+/// }
+/// assert_eq!(result.unwrap(), &payload[4..]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Encoder {
+    max_size: usize,
+    engine: Engine,
+    compress: bool,
+}
+
+/// Upper bound of fragments each engine's reassembly accepts.
+const MAX_FRAGMENTS_SOURCE: usize = 32;
+const MAX_FRAGMENTS_GOLDSRC: usize = 15;
+
+impl Encoder {
+    /// # Panics
+    ///
+    /// Panics if `max_size` is above 65535, which the split packet header
+    /// can't hold.
+    pub fn new(max_size: usize) -> Self {
+        let mut encoder = Self {
+            max_size: 0,
+            engine: Engine::Source,
+            compress: false,
+        };
+        encoder.max_size(max_size);
+        encoder
+    }
+
+    /// Maximum size of datagrams.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_size` is above 65535, which the split packet header
+    /// can't hold.
+    pub fn max_size(&mut self, max_size: usize) -> &mut Self {
+        assert!(
+            max_size <= u16::MAX as usize,
+            "max size {} above 65535",
+            max_size
+        );
+        self.max_size = max_size;
+        self
+    }
+
+    pub fn engine(&mut self, engine: Engine) -> &mut Self {
+        self.engine = engine;
+        self
+    }
+
+    /// Whether split responses are bzip2 compressed. Only supported by the
+    /// Source split packet header, defaults to `false`.
+    pub fn compress(&mut self, compress: bool) -> &mut Self {
+        self.compress = compress;
+        self
+    }
+
+    /// Splits `payload` into datagrams, tagging fragments with `id`.
+    ///
+    /// The high bit of `id` marks compression and is set or cleared as
+    /// configured.
+    pub fn encode(&self, id: i32, payload: &[u8]) -> Result<Vec<Vec<u8>>> {
+        if payload.len() <= self.max_size {
+            return Ok(vec![payload.to_vec()]);
+        }
+
+        match self.engine {
+            Engine::Source if self.compress => self.encode_source_bz2(id, payload),
+            Engine::Source => self.encode_source(id as u32 & 0x7FFFFFFF, payload, None),
+            Engine::GoldSrc => self.encode_goldsrc(id, payload),
+        }
+    }
+
+    fn encode_source_bz2(&self, id: i32, payload: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let mut encoder = BzEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(payload)?;
+        let compressed = encoder.finish()?;
+
+        let bz2 = (payload.len() as u32, CRC32.checksum(payload));
+        self.encode_source(id as u32 | 0x80000000, &compressed, Some(bz2))
+    }
+
+    fn encode_source(
+        &self,
+        id: u32,
+        payload: &[u8],
+        bz2: Option<(u32, u32)>,
+    ) -> Result<Vec<Vec<u8>>> {
+        let header = if bz2.is_some() {
+            OFS_MP_SS_PAYLOAD_BZ2
+        } else {
+            OFS_MP_SS_PAYLOAD
+        };
+        let size = self
+            .max_size
+            .checked_sub(header)
+            .filter(|size| *size > 0)
+            .ok_or(Error::Other("Max size too small for split packets"))?;
+        let total = payload.len().div_ceil(size);
+        if total > MAX_FRAGMENTS_SOURCE {
+            return Err(Error::Other("Payload too large for split packets"));
+        }
+
+        let mut datagrams = Vec::with_capacity(total);
+        for (number, chunk) in payload.chunks(size).enumerate() {
+            let mut datagram = Vec::with_capacity(self.max_size);
+            datagram.extend(MULTI_PACKET.to_le_bytes());
+            datagram.extend(id.to_le_bytes());
+            datagram.push(total as u8);
+            datagram.push(number as u8);
+            datagram.extend((self.max_size as u16).to_le_bytes());
+            if let (0, Some((decompressed_size, checksum))) = (number, bz2) {
+                datagram.extend(decompressed_size.to_le_bytes());
+                datagram.extend(checksum.to_le_bytes());
+            }
+            datagram.extend(chunk);
+            datagrams.push(datagram);
+        }
+
+        Ok(datagrams)
+    }
+
+    fn encode_goldsrc(&self, id: i32, payload: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let size = self
+            .max_size
+            .checked_sub(OFS_MP_GS_PAYLOAD)
+            .filter(|size| *size > 0)
+            .ok_or(Error::Other("Max size too small for split packets"))?;
+        let total = payload.len().div_ceil(size);
+        if total > MAX_FRAGMENTS_GOLDSRC {
+            return Err(Error::Other("Payload too large for split packets"));
+        }
+
+        let mut datagrams = Vec::with_capacity(total);
+        for (number, chunk) in payload.chunks(size).enumerate() {
+            let mut datagram = Vec::with_capacity(self.max_size);
+            datagram.extend(MULTI_PACKET.to_le_bytes());
+            datagram.extend(id.to_le_bytes());
+            datagram.push(((number as u8) << 4) | total as u8);
+            datagram.extend(chunk);
+            datagrams.push(datagram);
+        }
+
+        Ok(datagrams)
+    }
+}
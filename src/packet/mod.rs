@@ -0,0 +1,81 @@
+//! Transport independent handling of A2S datagrams.
+//!
+//! Nothing in here touches a socket: datagrams are fed in by the caller and
+//! complete payloads (or typed errors) come out, which lets both the sync and
+//! async clients share one implementation, and lets users drive it from their
+//! own sockets. [`Encoder`] does the reverse for servers.
+
+macro_rules! read_buffer_offset {
+    ($buf:expr, $offset:expr, i8) => {
+        $buf[$offset].into()
+    };
+    ($buf:expr, $offset:expr, u8) => {
+        $buf[$offset].into()
+    };
+    ($buf:expr, $offset:expr, i16) => {
+        i16::from_le_bytes([$buf[$offset], $buf[$offset + 1]])
+    };
+    ($buf:expr, $offset:expr, u16) => {
+        u16::from_le_bytes([$buf[$offset], $buf[$offset + 1]])
+    };
+    ($buf:expr, $offset:expr, i32) => {
+        i32::from_le_bytes([
+            $buf[$offset],
+            $buf[$offset + 1],
+            $buf[$offset + 2],
+            $buf[$offset + 3],
+        ])
+    };
+    ($buf:expr, $offset:expr, u32) => {
+        u32::from_le_bytes([
+            $buf[$offset],
+            $buf[$offset + 1],
+            $buf[$offset + 2],
+            $buf[$offset + 3],
+        ])
+    };
+    ($buf:expr, $offset:expr, i64) => {
+        i64::from_le_bytes([
+            $buf[$offset],
+            $buf[$offset + 1],
+            $buf[$offset + 2],
+            $buf[$offset + 3],
+            $buf[$offset + 4],
+            $buf[$offset + 5],
+            $buf[$offset + 6],
+            $buf[$offset + 7],
+        ])
+    };
+    ($buf:expr, $offset:expr, u64) => {
+        u64::from_le_bytes([
+            $buf[$offset],
+            $buf[$offset + 1],
+            $buf[$offset + 2],
+            $buf[$offset + 3],
+            $buf[$offset + 4],
+            $buf[$offset + 5],
+            $buf[$offset + 6],
+            $buf[$offset + 7],
+        ])
+    };
+}
+
+/// Engine generation of the queried server.
+///
+/// Source and GoldSrc servers share the A2S query format but differ in how
+/// they split responses across multiple datagrams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Engine {
+    /// Source engine and above, with the Source split packet header.
+    #[default]
+    Source,
+
+    /// GoldSrc (Half-Life 1) engine, with the single byte split packet header.
+    GoldSrc,
+}
+
+pub mod encoder;
+pub mod reassembler;
+
+pub use crate::packet::encoder::Encoder;
+pub use crate::packet::reassembler::Reassembler;
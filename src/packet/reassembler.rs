@@ -0,0 +1,253 @@
+use crate::constants::*;
+use crate::errors::{Error, Result};
+use crate::packet::Engine;
+use crate::CRC32;
+use bzip2::read::BzDecoder;
+use std::io::Read;
+
+/// Upper bound of fragments a single response may be split into.
+const MAX_FRAGMENTS: usize = 32;
+
+/// Upper bound of a decompressed bzip2 payload.
+const MAX_BZ2_SIZE: u32 = 1024 * 1024;
+
+/// Reassembler is a sans-IO state machine turning raw A2S datagrams into
+/// complete response payloads.
+///
+/// Feed every datagram received from a server into [`Reassembler::push`]; it
+/// returns `Ok(None)` while a split response is still missing fragments and
+/// `Ok(Some(payload))` once the response is complete. The returned payload has
+/// the `0xFFFFFFFF` header stripped and starts at the response type byte.
+///
+/// # Example
+///
+/// ```rust
+/// use crowbar_a2s::Reassembler;
+///
+/// let mut reassembler = Reassembler::new(1400);
+/// let payload = reassembler
+///     .push(&[0xFF, 0xFF, 0xFF, 0xFF, 0x41, 0x01, 0x02, 0x03, 0x04])
+///     .unwrap();
+///
+/// assert_eq!(payload, Some(vec![0x41, 0x01, 0x02, 0x03, 0x04]));
+/// ```
+#[derive(Debug)]
+pub struct Reassembler {
+    max_size: usize,
+    engine: Engine,
+    pending: Option<Pending>,
+}
+
+#[derive(Debug)]
+struct Pending {
+    id: i32,
+    total: usize,
+    switching_size: usize,
+    compressed: bool,
+    fragments: Vec<PacketFragment>,
+    /// Decompressed size and CRC32, carried by fragment 0 of compressed responses
+    bz2: Option<(u32, u32)>,
+}
+
+impl Reassembler {
+    pub fn new(max_size: usize) -> Self {
+        Self::with_engine(max_size, Engine::Source)
+    }
+
+    /// Creates a reassembler for the split packet header used by `engine`.
+    pub fn with_engine(max_size: usize, engine: Engine) -> Self {
+        Self {
+            max_size,
+            engine,
+            pending: None,
+        }
+    }
+
+    /// Whether a split response has been started but not completed yet.
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Size of the buffer the next datagram should be received into.
+    pub fn recv_size(&self) -> usize {
+        match &self.pending {
+            Some(pending) => pending.switching_size.max(self.max_size),
+            None => self.max_size,
+        }
+    }
+
+    /// Drops any partially received response.
+    pub fn reset(&mut self) {
+        self.pending = None;
+    }
+
+    /// Feeds one datagram into the reassembler.
+    ///
+    /// On error the partially received response is dropped, so the
+    /// reassembler can be reused for the next request.
+    pub fn push(&mut self, datagram: &[u8]) -> Result<Option<Vec<u8>>> {
+        let result = self.push_inner(datagram);
+        if !matches!(result, Ok(None)) {
+            self.pending = None;
+        }
+        result
+    }
+
+    fn push_inner(&mut self, data: &[u8]) -> Result<Option<Vec<u8>>> {
+        if data.len() < OFS_SP_PAYLOAD {
+            return Err(Error::InvalidResponse);
+        }
+
+        let header = read_buffer_offset!(data, OFS_HEADER, i32);
+
+        if header == SINGLE_PACKET {
+            if self.pending.is_some() {
+                return Err(Error::InvalidResponse);
+            }
+            return Ok(Some(data[OFS_SP_PAYLOAD..].to_vec()));
+        } else if header != MULTI_PACKET {
+            return Err(Error::InvalidResponse);
+        }
+
+        let (id, total, number, switching_size, offset) = match self.engine {
+            Engine::Source => {
+                // ID - long (4 bytes)
+                // Total - byte (1 byte)
+                // Number - byte (1 byte)
+                // Size - short (2 bytes)
+                if data.len() < OFS_MP_SS_PAYLOAD {
+                    return Err(Error::InvalidResponse);
+                }
+
+                (
+                    read_buffer_offset!(data, OFS_MP_ID, i32),
+                    usize::from(data[OFS_MP_SS_TOTAL]),
+                    data[OFS_MP_SS_NUMBER],
+                    usize::from(read_buffer_offset!(data, OFS_MP_SS_SIZE, u16)),
+                    OFS_MP_SS_PAYLOAD,
+                )
+            }
+            Engine::GoldSrc => {
+                // ID - long (4 bytes)
+                // Packet number - upper 4 bits of a byte
+                // Total packets - lower 4 bits of a byte
+                if data.len() < OFS_MP_GS_PAYLOAD {
+                    return Err(Error::InvalidResponse);
+                }
+
+                let packet = data[OFS_MP_GS_PACKET];
+                (
+                    read_buffer_offset!(data, OFS_MP_ID, i32),
+                    usize::from(packet & 0x0F),
+                    packet >> 4,
+                    self.max_size,
+                    OFS_MP_GS_PAYLOAD,
+                )
+            }
+        };
+
+        match &self.pending {
+            Some(pending) => {
+                if pending.id != id {
+                    return Err(Error::MismatchID);
+                }
+                if pending.total != total {
+                    return Err(Error::InvalidResponse);
+                }
+            }
+            None => {
+                // Sanity check
+                if (switching_size > self.max_size) || (total > MAX_FRAGMENTS) || (total == 0) {
+                    return Err(Error::InvalidResponse);
+                }
+            }
+        }
+
+        let compressed = self.engine == Engine::Source && id as u32 & 0x80000000 != 0;
+        let pending = self.pending.get_or_insert_with(|| Pending {
+            id,
+            total,
+            switching_size,
+            compressed,
+            fragments: Vec::with_capacity(0),
+            bz2: None,
+        });
+        pending.fragments.try_reserve(total)?;
+
+        if usize::from(number) >= pending.total {
+            return Err(Error::InvalidResponse);
+        }
+
+        let offset = if pending.compressed && number == 0 {
+            if data.len() < OFS_MP_SS_PAYLOAD_BZ2 {
+                return Err(Error::InvalidResponse);
+            }
+            pending.bz2 = Some((
+                read_buffer_offset!(data, OFS_MP_SS_BZ2_SIZE, u32),
+                read_buffer_offset!(data, OFS_MP_SS_BZ2_CRC, u32),
+            ));
+            OFS_MP_SS_PAYLOAD_BZ2
+        } else {
+            offset
+        };
+
+        // Servers may resend a fragment; keep the latest copy only.
+        pending.fragments.retain(|f| f.number != number);
+        pending.fragments.push(PacketFragment {
+            number,
+            payload: Vec::from(&data[offset..]),
+        });
+
+        if pending.fragments.len() < pending.total {
+            return Ok(None);
+        }
+
+        let pending = self.pending.take().ok_or(Error::InvalidResponse)?;
+        pending.assemble(self.max_size).map(Some)
+    }
+}
+
+impl Pending {
+    fn assemble(mut self, max_size: usize) -> Result<Vec<u8>> {
+        self.fragments.sort_by_key(|p| p.number);
+
+        let mut aggregation = Vec::with_capacity(0);
+        aggregation.try_reserve(self.total * max_size)?;
+
+        for p in self.fragments.drain(..) {
+            aggregation.extend(p.payload);
+        }
+
+        let payload = match self.bz2 {
+            Some((decompressed_size, checksum)) => {
+                if decompressed_size > MAX_BZ2_SIZE {
+                    return Err(Error::InvalidBz2Size);
+                }
+
+                let mut decompressed = Vec::with_capacity(0);
+                decompressed.try_reserve(decompressed_size as usize)?;
+                decompressed.resize(decompressed_size as usize, 0);
+
+                BzDecoder::new(&aggregation[..]).read_exact(&mut decompressed)?;
+
+                if CRC32.checksum(&decompressed) != checksum {
+                    return Err(Error::CheckSumMismatch);
+                }
+
+                decompressed
+            }
+            None if self.compressed => return Err(Error::InvalidResponse),
+            None => aggregation,
+        };
+
+        // The reassembled payload carries its own single packet header
+        // (0xFFFFFFFF), which we'd rather skip
+        if payload.len() < OFS_SP_PAYLOAD
+            || read_buffer_offset!(payload, OFS_HEADER, i32) != SINGLE_PACKET
+        {
+            return Err(Error::InvalidResponse);
+        }
+
+        Ok(payload[OFS_SP_PAYLOAD..].to_vec())
+    }
+}
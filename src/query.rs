@@ -0,0 +1,331 @@
+//! Transport independent A2S query state machine.
+//!
+//! [`Query`] combines the challenge handshake with split packet reassembly:
+//! it is fed the datagrams received from one server and either asks for its
+//! request to be resent (with the challenge the server issued) or yields the
+//! complete response payload.
+
+use crate::errors::{Error, QueryError, Result, Stage};
+use crate::packet::{Engine, Reassembler};
+use crate::types::{INFO_REQUEST, PING_REQUEST, PLAYER_REQUEST, RULES_REQUEST};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Upper bound of challenges accepted for one query, so misbehaving servers
+/// can't keep a query alive forever.
+const MAX_CHALLENGES: usize = 3;
+
+/// A2S_SERVERQUERY_GETCHALLENGE, the deprecated standalone challenge request.
+pub const GETCHALLENGE_REQUEST: [u8; 5] = [0xFF, 0xFF, 0xFF, 0xFF, 0x57];
+
+/// Kind of A2S request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueryKind {
+    Info,
+    Players,
+    Rules,
+    /// A2A_PING, which servers answer without a challenge, if at all.
+    Ping,
+}
+
+/// How A2S_PLAYER and A2S_RULES queries ask the server for a challenge.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ChallengeMode {
+    /// Sends the request with a `-1` challenge, as current servers expect.
+    #[default]
+    Request,
+
+    /// Sends [`GETCHALLENGE_REQUEST`] first, which is all some older games
+    /// and third-party servers answer.
+    Legacy,
+
+    /// Like [`ChallengeMode::Request`], switching to
+    /// [`ChallengeMode::Legacy`] when the server doesn't answer.
+    Fallback,
+}
+
+/// Outcome of feeding a datagram into a [`Query`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum QueryStatus {
+    /// More datagrams are expected.
+    Pending,
+
+    /// The server issued a challenge; send [`Query::request`] again.
+    Resend,
+
+    /// The response payload, starting at the response type byte.
+    Complete(Vec<u8>),
+}
+
+/// Query is a sans-IO state machine for one A2S request to one server.
+#[derive(Debug)]
+pub struct Query {
+    kind: QueryKind,
+    request: Vec<u8>,
+    reassembler: Reassembler,
+    challenge: Option<i32>,
+    challenges: usize,
+    mode: ChallengeMode,
+    stage: Stage,
+    /// Datagrams received for the current response
+    received: usize,
+    capture: bool,
+    datagrams: Vec<Vec<u8>>,
+}
+
+impl Query {
+    pub fn new(kind: QueryKind, max_size: usize, engine: Engine) -> Self {
+        let request = match kind {
+            QueryKind::Info => INFO_REQUEST.to_vec(),
+            QueryKind::Players => Self::challenge_request(&PLAYER_REQUEST, -1),
+            QueryKind::Rules => Self::challenge_request(&RULES_REQUEST, -1),
+            QueryKind::Ping => PING_REQUEST.to_vec(),
+        };
+
+        Self {
+            kind,
+            request,
+            reassembler: Reassembler::with_engine(max_size, engine),
+            challenge: None,
+            challenges: 0,
+            mode: ChallengeMode::default(),
+            stage: Stage::Fragment(0),
+            received: 0,
+            capture: false,
+            datagrams: Vec::new(),
+        }
+    }
+
+    fn challenge_request(header: &[u8], challenge: i32) -> Vec<u8> {
+        let mut request = Vec::with_capacity(header.len() + 4);
+        request.extend(header);
+        request.extend(challenge.to_le_bytes());
+        request
+    }
+
+    pub fn kind(&self) -> QueryKind {
+        self.kind
+    }
+
+    fn header(&self) -> &'static [u8] {
+        match self.kind {
+            QueryKind::Info => &INFO_REQUEST,
+            QueryKind::Players => &PLAYER_REQUEST,
+            QueryKind::Rules => &RULES_REQUEST,
+            QueryKind::Ping => &PING_REQUEST,
+        }
+    }
+
+    /// Sets how a challenge is asked for. A2S_INFO challenges are only ever
+    /// issued in response to the request itself, so they are unaffected, as
+    /// is A2A_PING.
+    pub fn challenge_mode(&mut self, mode: ChallengeMode) -> &mut Self {
+        self.mode = mode;
+        if mode == ChallengeMode::Legacy && self.challenge.is_none() {
+            self.legacy_request();
+        }
+        self
+    }
+
+    fn needs_challenge(&self) -> bool {
+        matches!(self.kind, QueryKind::Players | QueryKind::Rules)
+    }
+
+    fn legacy_request(&mut self) {
+        if self.needs_challenge() {
+            self.request = GETCHALLENGE_REQUEST.to_vec();
+        }
+    }
+
+    /// Switches to [`ChallengeMode::Legacy`] after the server didn't answer,
+    /// if the query is in [`ChallengeMode::Fallback`] and no challenge was
+    /// received yet. Returns whether [`Query::request`] changed and should
+    /// be sent.
+    pub fn fallback(&mut self) -> bool {
+        if self.mode != ChallengeMode::Fallback
+            || !self.needs_challenge()
+            || self.challenge.is_some()
+        {
+            return false;
+        }
+
+        self.mode = ChallengeMode::Legacy;
+        self.legacy_request();
+        self.retry();
+        true
+    }
+
+    /// Sends `challenge`, issued by the server to an earlier query, right
+    /// away instead of requesting one first. A2A_PING carries no challenge.
+    pub fn use_challenge(&mut self, challenge: i32) -> &mut Self {
+        if self.kind == QueryKind::Ping {
+            return self;
+        }
+        self.request = Self::challenge_request(self.header(), challenge);
+        self.challenge = Some(challenge);
+        self
+    }
+
+    /// Challenge sent with the request, if any.
+    pub fn challenge(&self) -> Option<i32> {
+        self.challenge
+    }
+
+    /// Datagram to send to the server.
+    pub fn request(&self) -> &[u8] {
+        &self.request
+    }
+
+    /// Size of the buffer the next datagram should be received into.
+    pub fn recv_size(&self) -> usize {
+        self.reassembler.recv_size()
+    }
+
+    /// Prepares the query to be sent again after a datagram got lost,
+    /// dropping any partially received response but keeping the challenge.
+    /// A stale challenge is answered with a fresh one by the server.
+    pub fn retry(&mut self) {
+        self.reassembler.reset();
+        self.next_response();
+    }
+
+    fn next_response(&mut self) {
+        self.received = 0;
+        self.datagrams.clear();
+    }
+
+    /// Keeps the datagrams of the current response, so errors can carry
+    /// them. Disabled by default.
+    pub fn capture(&mut self, enabled: bool) -> &mut Self {
+        self.capture = enabled;
+        self
+    }
+
+    /// Wraps `source`, returned by [`Query::push`] or by parsing the
+    /// payload, in an [`Error::Query`] telling where the query to `server`
+    /// failed.
+    pub fn error(&mut self, server: SocketAddr, source: Error) -> Error {
+        Error::Query(Box::new(QueryError {
+            server,
+            kind: self.kind,
+            stage: self.stage,
+            datagrams: std::mem::take(&mut self.datagrams),
+            source,
+        }))
+    }
+
+    /// Wraps `source`, a timeout or socket error met waiting for the next
+    /// datagram, in an [`Error::Query`] at the fragment waited for.
+    pub fn recv_error(&mut self, server: SocketAddr, source: Error) -> Error {
+        self.stage = Stage::Fragment(self.received);
+        self.error(server, source)
+    }
+
+    /// Wraps `source`, a socket error met sending [`Query::request`], in an
+    /// [`Error::Query`].
+    pub fn send_error(&mut self, server: SocketAddr, source: Error) -> Error {
+        self.stage = Stage::Request;
+        self.error(server, source)
+    }
+
+    /// Feeds one datagram received from the server.
+    pub fn push(&mut self, datagram: &[u8]) -> Result<QueryStatus> {
+        if self.capture {
+            self.datagrams.push(datagram.to_vec());
+        }
+        self.stage = Stage::Fragment(self.received);
+        self.received += 1;
+
+        let payload = match self.reassembler.push(datagram) {
+            Ok(Some(payload)) => payload,
+            Ok(None) => return Ok(QueryStatus::Pending),
+            Err(err) => {
+                // Reading the bzip2 stream is the only IO
+                if matches!(
+                    err,
+                    Error::InvalidBz2Size | Error::CheckSumMismatch | Error::Io(_)
+                ) {
+                    self.stage = Stage::Decompression;
+                }
+                return Err(err);
+            }
+        };
+
+        if payload.first() != Some(&b'A') {
+            self.stage = Stage::Parse;
+            return Ok(QueryStatus::Complete(payload));
+        }
+
+        self.stage = Stage::Challenge;
+        if payload.len() < 5 || self.challenges >= MAX_CHALLENGES {
+            return Err(Error::InvalidResponse);
+        }
+        self.challenges += 1;
+
+        let challenge = i32::from_le_bytes([payload[1], payload[2], payload[3], payload[4]]);
+        self.use_challenge(challenge);
+        self.next_response();
+
+        Ok(QueryStatus::Resend)
+    }
+}
+
+/// Challenges issued by servers, so later queries can skip the round-trip
+/// requesting one.
+///
+/// Servers issue a fresh challenge whenever a cached one went stale, which
+/// [`Query`] handles like any other challenge.
+#[derive(Debug)]
+pub struct ChallengeCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<SocketAddr, (i32, Instant)>>,
+}
+
+impl ChallengeCache {
+    /// Creates a cache keeping challenges for `ttl` after they were issued.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Challenge issued by `server`, unless it expired.
+    pub fn get(&self, server: SocketAddr) -> Option<i32> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        match entries.get(&server) {
+            Some((challenge, issued)) if issued.elapsed() < self.ttl => Some(*challenge),
+            Some(_) => {
+                entries.remove(&server);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, server: SocketAddr, challenge: i32) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        // Servers which are never queried again would stay forever
+        if entries.len().is_multiple_of(1024) {
+            entries.retain(|_, (_, issued)| issued.elapsed() < self.ttl);
+        }
+        entries.insert(server, (challenge, Instant::now()));
+    }
+
+    /// Starts `query` with the challenge cached for `server`, if any.
+    pub fn prepare(&self, server: SocketAddr, query: &mut Query) {
+        if let Some(challenge) = self.get(server) {
+            query.use_challenge(challenge);
+        }
+    }
+
+    /// Remembers the challenge `server` issued to `query`, after it asked for
+    /// the request to be resent.
+    pub fn update(&self, server: SocketAddr, query: &Query) {
+        if let Some(challenge) = query.challenge() {
+            self.insert(server, challenge);
+        }
+    }
+}
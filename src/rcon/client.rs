@@ -0,0 +1,93 @@
+use crate::errors::{Error, Result};
+use crate::rcon::{Auth, Command, Packet, PacketReader};
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// RconClient is a synchronous client for the Source RCON protocol.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// let mut rcon = crowbar_a2s::Builder::new()
+///     .connect_rcon("127.0.0.1:27015", "password")
+///     .unwrap();
+///
+/// println!("{}", rcon.exec("status").unwrap());
+/// ```
+pub struct RconClient {
+    stream: TcpStream,
+    reader: PacketReader,
+    next_id: i32,
+}
+
+impl RconClient {
+    pub(crate) fn connect<A: ToSocketAddrs>(
+        addr: A,
+        password: &str,
+        timeout: Duration,
+    ) -> Result<Self> {
+        let addr = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or(Error::Other("RCON address did not resolve"))?;
+        let stream = TcpStream::connect_timeout(&addr, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+
+        let mut client = Self {
+            stream,
+            reader: PacketReader::new(),
+            next_id: 1,
+        };
+        client.auth(password)?;
+
+        Ok(client)
+    }
+
+    fn next_id(&mut self) -> i32 {
+        let id = self.next_id;
+        self.next_id = self.next_id.checked_add(1).unwrap_or(1);
+        id
+    }
+
+    fn recv(&mut self) -> Result<Packet> {
+        let mut data = [0; 4096];
+        loop {
+            if let Some(packet) = self.reader.next_packet()? {
+                return Ok(packet);
+            }
+
+            let read = self.stream.read(&mut data)?;
+            if read == 0 {
+                return Err(Error::Other("RCON connection closed"));
+            }
+            self.reader.extend(&data[..read]);
+        }
+    }
+
+    fn auth(&mut self, password: &str) -> Result<()> {
+        let id = self.next_id();
+        let (auth, request) = Auth::request(id, password);
+        self.stream.write_all(&request)?;
+
+        while !auth.push(&self.recv()?)? {}
+
+        Ok(())
+    }
+
+    /// Executes `command`, returning its complete output.
+    pub fn exec(&mut self, command: &str) -> Result<String> {
+        let id = self.next_id();
+        let terminator = self.next_id();
+        let (mut command, request) = Command::request(id, terminator, command);
+        self.stream.write_all(&request)?;
+
+        loop {
+            let packet = self.recv()?;
+            if command.push(packet) {
+                return Ok(command.into_response());
+            }
+        }
+    }
+}
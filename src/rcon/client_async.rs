@@ -0,0 +1,80 @@
+use crate::errors::{Error, Result};
+use crate::rcon::{Auth, Command, Packet, PacketReader};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio::time;
+
+/// RconClientAsync is an asynchronous client for the Source RCON protocol.
+pub struct RconClientAsync {
+    stream: TcpStream,
+    reader: PacketReader,
+    timeout: Duration,
+    next_id: i32,
+}
+
+impl RconClientAsync {
+    pub(crate) async fn connect<A: ToSocketAddrs>(
+        addr: A,
+        password: &str,
+        timeout: Duration,
+    ) -> Result<Self> {
+        let stream = future_timeout!(timeout, TcpStream::connect(addr))?;
+
+        let mut client = Self {
+            stream,
+            reader: PacketReader::new(),
+            timeout,
+            next_id: 1,
+        };
+        client.auth(password).await?;
+
+        Ok(client)
+    }
+
+    fn next_id(&mut self) -> i32 {
+        let id = self.next_id;
+        self.next_id = self.next_id.checked_add(1).unwrap_or(1);
+        id
+    }
+
+    async fn recv(&mut self) -> Result<Packet> {
+        let mut data = [0; 4096];
+        loop {
+            if let Some(packet) = self.reader.next_packet()? {
+                return Ok(packet);
+            }
+
+            let read = future_timeout!(self.timeout, self.stream.read(&mut data))?;
+            if read == 0 {
+                return Err(Error::Other("RCON connection closed"));
+            }
+            self.reader.extend(&data[..read]);
+        }
+    }
+
+    async fn auth(&mut self, password: &str) -> Result<()> {
+        let id = self.next_id();
+        let (auth, request) = Auth::request(id, password);
+        future_timeout!(self.timeout, self.stream.write_all(&request))?;
+
+        while !auth.push(&self.recv().await?)? {}
+
+        Ok(())
+    }
+
+    /// Executes `command`, returning its complete output.
+    pub async fn exec(&mut self, command: &str) -> Result<String> {
+        let id = self.next_id();
+        let terminator = self.next_id();
+        let (mut command, request) = Command::request(id, terminator, command);
+        future_timeout!(self.timeout, self.stream.write_all(&request))?;
+
+        loop {
+            let packet = self.recv().await?;
+            if command.push(packet) {
+                return Ok(command.into_response());
+            }
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! GoldSrc RCON protocol over UDP.
+//!
+//! Clients request a challenge with `challenge rcon` and then send
+//! `rcon <challenge> "<password>" <command>`. Both requests and responses use
+//! the `0xFFFFFFFF` connectionless header, and long responses are split like
+//! GoldSrc query responses, or sent as several print responses. The UDP
+//! query clients implement it with `goldsrc_rcon`.
+
+use crate::errors::{Error, Result};
+use std::time::Duration;
+
+pub const CHALLENGE_RCON_REQUEST: &[u8] = b"\xFF\xFF\xFF\xFFchallenge rcon\n";
+
+const CHALLENGE_RCON_RESPONSE: &str = "challenge rcon ";
+
+/// Header byte of A2A_PRINT responses carrying command output.
+const PRINT_RESPONSE_HEADER: u8 = b'l';
+
+const BAD_PASSWORD: &str = "Bad rcon_password.";
+
+/// How long to wait for more output after a print response, as long output
+/// is sent as several of them.
+pub const PRINT_GAP: Duration = Duration::from_millis(100);
+
+/// Parses the challenge from a reassembled `challenge rcon` response.
+pub fn parse_challenge(payload: &[u8]) -> Result<String> {
+    let response = String::from_utf8_lossy(payload);
+    let challenge = response
+        .trim_end_matches(['\0', '\n'])
+        .strip_prefix(CHALLENGE_RCON_RESPONSE)
+        .ok_or(Error::InvalidResponse)?;
+
+    if challenge.is_empty() || !challenge.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(Error::InvalidResponse);
+    }
+
+    Ok(challenge.to_string())
+}
+
+/// Builds the command request. The password is sent quoted, so it can't
+/// hold quotes or line breaks.
+pub fn command_request(challenge: &str, password: &str, command: &str) -> Result<Vec<u8>> {
+    if password.contains(['"', '\n', '\r']) {
+        return Err(Error::Other(
+            "RCON password can't contain quotes or line breaks",
+        ));
+    }
+
+    let mut request = vec![0xFF; 4];
+    request.extend(format!("rcon {challenge} \"{password}\" {command}\n").as_bytes());
+    Ok(request)
+}
+
+/// Parses the command output from a reassembled response.
+pub fn parse_response(payload: &[u8]) -> Result<String> {
+    let output = match payload.split_first() {
+        Some((&PRINT_RESPONSE_HEADER, output)) => output,
+        _ => return Err(Error::InvalidResponse),
+    };
+
+    let output = String::from_utf8_lossy(output)
+        .trim_end_matches('\0')
+        .to_string();
+    if output.trim_end() == BAD_PASSWORD {
+        return Err(Error::RconAuth);
+    }
+
+    Ok(output)
+}
+
+/// Parses the command output spread over several reassembled responses.
+pub fn parse_responses(payloads: &[Vec<u8>]) -> Result<String> {
+    payloads
+        .iter()
+        .map(|payload| parse_response(payload))
+        .collect()
+}
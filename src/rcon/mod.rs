@@ -0,0 +1,205 @@
+//! Source RCON protocol over TCP.
+//!
+//! Packets are framed as little-endian `size`, `id` and `type` fields
+//! followed by a null-terminated body and an empty string. Responses to a
+//! command may span several `SERVERDATA_RESPONSE_VALUE` packets, so every
+//! command is followed by an empty `SERVERDATA_RESPONSE_VALUE` packet: the
+//! server mirrors it back after the last packet of the command's response.
+//!
+//! [`RconClient`] and [`RconClientAsync`] are created from a
+//! [`Builder`](crate::Builder) with `connect_rcon` and `connect_rcon_async`.
+//! The UDP based GoldSrc RCON is implemented by the query clients, see
+//! [`goldsrc`].
+
+#[cfg(feature = "sync")]
+mod client;
+#[cfg(feature = "async")]
+mod client_async;
+pub mod goldsrc;
+
+#[cfg(feature = "sync")]
+pub use crate::rcon::client::RconClient;
+#[cfg(feature = "async")]
+pub use crate::rcon::client_async::RconClientAsync;
+
+use crate::errors::{Error, Result};
+
+pub const SERVERDATA_AUTH: i32 = 3;
+pub const SERVERDATA_AUTH_RESPONSE: i32 = 2;
+pub const SERVERDATA_EXECCOMMAND: i32 = 2;
+pub const SERVERDATA_RESPONSE_VALUE: i32 = 0;
+
+/// Size of the id and type fields plus the two null terminators.
+const MIN_PACKET_SIZE: usize = 10;
+
+/// Upper bound of the size field accepted from servers.
+const MAX_PACKET_SIZE: usize = 16 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packet {
+    pub id: i32,
+
+    /// One of the `SERVERDATA_*` constants.
+    pub kind: i32,
+
+    pub body: Vec<u8>,
+}
+
+impl Packet {
+    pub fn new(id: i32, kind: i32, body: &[u8]) -> Self {
+        Self {
+            id,
+            kind,
+            body: body.to_vec(),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let size = MIN_PACKET_SIZE + self.body.len();
+        let mut bytes = Vec::with_capacity(4 + size);
+
+        bytes.extend((size as i32).to_le_bytes());
+        bytes.extend(self.id.to_le_bytes());
+        bytes.extend(self.kind.to_le_bytes());
+        bytes.extend(&self.body);
+        bytes.extend([0, 0]);
+
+        bytes
+    }
+
+    /// Number of bytes the packet starting at `data` takes up, once its size
+    /// field has been received.
+    pub fn frame_len(data: &[u8]) -> Result<Option<usize>> {
+        let size = match data.get(..4) {
+            Some(size) => i32::from_le_bytes([size[0], size[1], size[2], size[3]]),
+            None => return Ok(None),
+        };
+
+        if size < MIN_PACKET_SIZE as i32 || size > MAX_PACKET_SIZE as i32 {
+            return Err(Error::InvalidResponse);
+        }
+
+        Ok(Some(4 + size as usize))
+    }
+
+    /// Parses one complete frame, as delimited by [`Packet::frame_len`].
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if Self::frame_len(data)? != Some(data.len()) {
+            return Err(Error::InvalidResponse);
+        }
+
+        let id = i32::from_le_bytes([data[4], data[5], data[6], data[7]]);
+        let kind = i32::from_le_bytes([data[8], data[9], data[10], data[11]]);
+        let body = &data[12..data.len() - 2];
+
+        Ok(Self::new(id, kind, body))
+    }
+}
+
+/// Splits a TCP byte stream into packets.
+#[derive(Debug, Default)]
+pub struct PacketReader {
+    buffer: Vec<u8>,
+}
+
+impl PacketReader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends bytes received from the stream.
+    pub fn extend(&mut self, data: &[u8]) {
+        self.buffer.extend(data);
+    }
+
+    /// Takes the next complete packet, if any.
+    pub fn next_packet(&mut self) -> Result<Option<Packet>> {
+        let len = match Packet::frame_len(&self.buffer)? {
+            Some(len) if len <= self.buffer.len() => len,
+            _ => return Ok(None),
+        };
+
+        let packet = Packet::from_bytes(&self.buffer[..len])?;
+        self.buffer.drain(..len);
+
+        Ok(Some(packet))
+    }
+}
+
+/// Sans-IO state of the authentication exchange.
+#[cfg(any(feature = "sync", feature = "async"))]
+#[derive(Debug)]
+pub(crate) struct Auth {
+    id: i32,
+}
+
+#[cfg(any(feature = "sync", feature = "async"))]
+impl Auth {
+    pub(crate) fn request(id: i32, password: &str) -> (Self, Vec<u8>) {
+        let packet = Packet::new(id, SERVERDATA_AUTH, password.as_bytes());
+        (Self { id }, packet.to_bytes())
+    }
+
+    /// Feeds a received packet, returning whether authentication completed.
+    pub(crate) fn push(&self, packet: &Packet) -> Result<bool> {
+        // Servers send an empty SERVERDATA_RESPONSE_VALUE before the
+        // SERVERDATA_AUTH_RESPONSE, which is skipped
+        if packet.kind != SERVERDATA_AUTH_RESPONSE {
+            return Ok(false);
+        }
+
+        if packet.id == -1 {
+            return Err(Error::RconAuth);
+        }
+        if packet.id != self.id {
+            return Err(Error::MismatchID);
+        }
+
+        Ok(true)
+    }
+}
+
+/// Sans-IO state collecting the response to one command.
+#[cfg(any(feature = "sync", feature = "async"))]
+#[derive(Debug)]
+pub(crate) struct Command {
+    id: i32,
+    terminator: i32,
+    body: Vec<u8>,
+}
+
+#[cfg(any(feature = "sync", feature = "async"))]
+impl Command {
+    /// Builds the command packet, followed by the empty packet whose mirror
+    /// marks the end of the response.
+    pub(crate) fn request(id: i32, terminator: i32, command: &str) -> (Self, Vec<u8>) {
+        let mut bytes = Packet::new(id, SERVERDATA_EXECCOMMAND, command.as_bytes()).to_bytes();
+        bytes.extend(Packet::new(terminator, SERVERDATA_RESPONSE_VALUE, &[]).to_bytes());
+
+        let command = Self {
+            id,
+            terminator,
+            body: Vec::new(),
+        };
+        (command, bytes)
+    }
+
+    /// Feeds a received packet, returning whether the response is complete.
+    pub(crate) fn push(&mut self, packet: Packet) -> bool {
+        if packet.kind != SERVERDATA_RESPONSE_VALUE {
+            return false;
+        }
+
+        if packet.id == self.id {
+            self.body.extend(packet.body);
+            false
+        } else {
+            // Anything else is left over from earlier commands
+            packet.id == self.terminator
+        }
+    }
+
+    pub(crate) fn into_response(self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
@@ -0,0 +1,153 @@
+//! Retrying queries whose datagrams got lost.
+
+#[cfg(any(feature = "sync", feature = "async"))]
+use crate::errors::{Error, Result};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+#[cfg(any(feature = "sync", feature = "async"))]
+use std::io::ErrorKind;
+use std::time::Duration;
+#[cfg(any(feature = "sync", feature = "async"))]
+use std::time::Instant;
+
+/// How queries are retried when the server doesn't answer in time.
+///
+/// The `n`th retry waits `base_delay * 2^(n - 1)`, lengthened by a random
+/// fraction of up to `jitter` so clients don't retry in lockstep. Only
+/// timeouts are retried; a server sending garbage won't do better next time.
+///
+/// # Example
+///
+/// ```rust
+/// use crowbar_a2s::RetryPolicy;
+/// use std::time::Duration;
+///
+/// let client = crowbar_a2s::Builder::new()
+///     .timeout(Duration::from_secs(1))
+///     .retry(RetryPolicy {
+///         attempts: 3,
+///         deadline: Some(Duration::from_secs(5)),
+///         ..RetryPolicy::default()
+///     })
+///     .build_sync()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one. `1` never retries.
+    pub attempts: u32,
+    /// Wait before the first retry, doubled for every later one.
+    pub base_delay: Duration,
+    /// Fraction of the wait randomly added to it, between 0 and 1.
+    pub jitter: f64,
+    /// Time since the first attempt after which no retry is started, and by
+    /// which the last attempt gives up.
+    pub deadline: Option<Duration>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 1,
+            base_delay: Duration::from_millis(200),
+            jitter: 0.2,
+            deadline: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait before the `retry`th retry, counting from 1.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        let delay = self.base_delay.saturating_mul(factor);
+
+        let jitter = self.jitter.clamp(0.0, 1.0) * random();
+        delay.saturating_add(delay.mul_f64(jitter))
+    }
+}
+
+/// Whether `err` may be caused by a lost datagram.
+#[cfg(any(feature = "sync", feature = "async"))]
+pub(crate) fn is_retriable(err: &Error) -> bool {
+    match err {
+        Error::ErrTimeout => true,
+        Error::Io(err) => matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut),
+        Error::Query(context) => is_retriable(&context.source),
+        _ => false,
+    }
+}
+
+/// Whether a query failed for the server not answering in time, retried or
+/// not.
+#[cfg(any(feature = "sync", feature = "async"))]
+pub(crate) fn timed_out(err: &Error) -> bool {
+    match err {
+        Error::RetriesExhausted { source, .. } => is_retriable(source),
+        err => is_retriable(err),
+    }
+}
+
+/// Uniformly distributed in `[0, 1)`.
+fn random() -> f64 {
+    let bits = RandomState::new().build_hasher().finish() >> 11;
+    bits as f64 / (1u64 << 53) as f64
+}
+
+/// Attempts made for one query under a [`RetryPolicy`].
+#[cfg(any(feature = "sync", feature = "async"))]
+#[derive(Debug)]
+pub(crate) struct Backoff {
+    policy: RetryPolicy,
+    started: Instant,
+    attempts: u32,
+}
+
+#[cfg(any(feature = "sync", feature = "async"))]
+impl Backoff {
+    pub(crate) fn new(policy: RetryPolicy) -> Self {
+        Self {
+            policy,
+            started: Instant::now(),
+            attempts: 1,
+        }
+    }
+
+    /// Whether the query was sent again after an attempt failed.
+    #[cfg(feature = "async")]
+    pub(crate) fn retried(&self) -> bool {
+        self.attempts > 1
+    }
+
+    /// Timeout of the next attempt, cut short so it ends by the deadline.
+    pub(crate) fn timeout(&self, timeout: Duration) -> Duration {
+        match self.policy.deadline {
+            Some(deadline) => timeout.min(deadline.saturating_sub(self.started.elapsed())),
+            None => timeout,
+        }
+    }
+
+    /// Decides whether to retry after an attempt failed with `err`, returning
+    /// the wait before the next attempt or the error to give up with.
+    pub(crate) fn retry(&mut self, err: Error) -> Result<Duration> {
+        if self.policy.attempts <= 1 || !is_retriable(&err) {
+            return Err(err);
+        }
+
+        let delay = self.policy.delay(self.attempts);
+        let late = self
+            .policy
+            .deadline
+            .is_some_and(|deadline| self.started.elapsed() + delay >= deadline);
+
+        if self.attempts >= self.policy.attempts || late {
+            return Err(Error::RetriesExhausted {
+                attempts: self.attempts,
+                source: Box::new(err),
+            });
+        }
+
+        self.attempts += 1;
+        Ok(delay)
+    }
+}
@@ -0,0 +1,54 @@
+use crate::errors::{self, Error, Result};
+use crate::server::{Responder, ServerState};
+use std::net::SocketAddr;
+use tokio::net::{ToSocketAddrs, UdpSocket};
+
+/// A2SServerAsync answers A2S queries on a tokio UDP socket.
+pub struct A2SServerAsync<S> {
+    socket: UdpSocket,
+    responder: Responder<S>,
+}
+
+impl<S: ServerState> A2SServerAsync<S> {
+    pub async fn bind<A: ToSocketAddrs>(addr: A, state: S) -> Result<Self> {
+        Ok(Self {
+            socket: UdpSocket::bind(addr).await?,
+            responder: Responder::new(state),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    pub fn responder(&mut self) -> &mut Responder<S> {
+        &mut self.responder
+    }
+
+    /// Receives one datagram and answers it if it is a known request.
+    ///
+    /// Errors concerning a single client, such as sending to a spoofed
+    /// address, are returned as is, and don't mean the socket failed.
+    pub async fn serve_one(&self) -> Result<()> {
+        let mut data = [0; 1400];
+        let (read, client) = self.socket.recv_from(&mut data).await?;
+
+        for datagram in self.responder.respond(&data[..read], client)? {
+            self.socket.send_to(&datagram, client).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Answers queries until the socket fails. Responses which cannot be
+    /// encoded are dropped, and so are datagrams from or to clients which
+    /// can't be reached, see [`Self::serve_one`].
+    pub async fn run(&self) -> Result<()> {
+        loop {
+            match self.serve_one().await {
+                Err(Error::Io(err)) if !errors::is_transient(&err) => return Err(Error::Io(err)),
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! A2S server responder.
+//!
+//! [`Responder`] answers A2S_INFO, A2S_PLAYER, A2S_RULES and A2A_PING
+//! requests from a user provided [`ServerState`], issuing and validating
+//! challenges, without touching a socket. [`A2SServer`] and
+//! [`A2SServerAsync`] bind a UDP socket and drive it, so non-Source game
+//! backends can be queried like any Source server.
+
+#[cfg(feature = "async")]
+mod async_server;
+#[cfg(feature = "sync")]
+mod sync_server;
+
+#[cfg(feature = "async")]
+pub use crate::server::async_server::A2SServerAsync;
+#[cfg(feature = "sync")]
+pub use crate::server::sync_server::A2SServer;
+
+use crate::errors::Result;
+use crate::packet::Encoder;
+use crate::query::GETCHALLENGE_REQUEST;
+use crate::types::{
+    Info, Player, Rule, INFO_REQUEST, PING_REQUEST, PING_RESPONSE, PLAYER_REQUEST, RULES_REQUEST,
+};
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicI32, Ordering};
+
+pub const CHALLENGE_RESPONSE_HEADER: [u8; 5] = [0xFF, 0xFF, 0xFF, 0xFF, 0x41];
+
+/// Provides the current server state answered to queries.
+///
+/// Methods are called for every valid request, so implementations should
+/// return a cheap snapshot of the state.
+pub trait ServerState {
+    fn info(&self) -> Info;
+
+    fn players(&self) -> Vec<Player>;
+
+    fn rules(&self) -> Vec<Rule>;
+}
+
+/// Responder is a sans-IO A2S server, mapping request datagrams to response
+/// datagrams.
+///
+/// Responses larger than the configured max size are split with an
+/// [`Encoder`].
+pub struct Responder<S> {
+    state: S,
+    secret: RandomState,
+    info_challenge: bool,
+    encoder: Encoder,
+    next_id: AtomicI32,
+}
+
+impl<S: ServerState> Responder<S> {
+    pub fn new(state: S) -> Self {
+        Self {
+            state,
+            secret: RandomState::new(),
+            info_challenge: true,
+            encoder: Encoder::new(1400),
+            next_id: AtomicI32::new(0),
+        }
+    }
+
+    /// Maximum size of response datagrams. Defaults to 1400.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is above 65535, see [`Encoder::max_size`].
+    pub fn max_size(&mut self, size: usize) -> &mut Self {
+        self.encoder.max_size(size);
+        self
+    }
+
+    /// Encoder used to split large responses, e.g. to enable compression.
+    pub fn encoder(&mut self) -> &mut Encoder {
+        &mut self.encoder
+    }
+
+    /// Whether A2S_INFO requests without a valid challenge are answered with
+    /// a challenge, as Source servers do since 2020. Defaults to `true`.
+    pub fn info_challenge(&mut self, info_challenge: bool) -> &mut Self {
+        self.info_challenge = info_challenge;
+        self
+    }
+
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    /// Challenge number issued to `client`.
+    ///
+    /// Challenges are derived from the client IP address and a per responder
+    /// secret, so no state is kept per client. Like Source servers, the port
+    /// is not part of it, so clients may query from several sockets.
+    pub fn challenge(&self, client: SocketAddr) -> i32 {
+        let challenge = self.secret.hash_one(client.ip()) as i32;
+        // -1 requests a challenge, so it can never be one
+        if challenge == -1 {
+            0
+        } else {
+            challenge
+        }
+    }
+
+    fn challenge_response(&self, client: SocketAddr) -> Vec<u8> {
+        let mut response = CHALLENGE_RESPONSE_HEADER.to_vec();
+        response.extend(self.challenge(client).to_le_bytes());
+        response
+    }
+
+    fn read_challenge(data: &[u8]) -> Option<i32> {
+        let bytes = data.get(..4)?;
+        Some(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Builds the response datagrams to a request datagram received from
+    /// `client`. Datagrams which are not a request this responder
+    /// understands get no response.
+    pub fn respond(&self, request: &[u8], client: SocketAddr) -> Result<Vec<Vec<u8>>> {
+        let response = match self.response(request, client) {
+            Some(response) => response,
+            None => return Ok(Vec::new()),
+        };
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.encoder.encode(id, &response)
+    }
+
+    fn response(&self, request: &[u8], client: SocketAddr) -> Option<Vec<u8>> {
+        if request.starts_with(&INFO_REQUEST) {
+            let challenge = Self::read_challenge(&request[INFO_REQUEST.len()..]);
+            if self.info_challenge && challenge != Some(self.challenge(client)) {
+                return Some(self.challenge_response(client));
+            }
+            return Some(self.state.info().to_bytes());
+        }
+
+        if request.starts_with(&PING_REQUEST) {
+            let mut response = vec![0xFF, 0xFF, 0xFF, 0xFF, PING_RESPONSE];
+            response.extend(b"00000000000000\0");
+            return Some(response);
+        }
+
+        // Deprecated standalone challenge request, still sent by old clients
+        if request.starts_with(&GETCHALLENGE_REQUEST) {
+            return Some(self.challenge_response(client));
+        }
+
+        if request.len() < PLAYER_REQUEST.len() || request[..4] != PLAYER_REQUEST[..4] {
+            return None;
+        }
+
+        let header = request[4];
+        if header != PLAYER_REQUEST[4] && header != RULES_REQUEST[4] {
+            return None;
+        }
+
+        let challenge = Self::read_challenge(&request[5..])?;
+        if challenge != self.challenge(client) {
+            return Some(self.challenge_response(client));
+        }
+
+        if header == PLAYER_REQUEST[4] {
+            Some(Player::vec_to_bytes(self.state.players()))
+        } else {
+            Some(Rule::vec_to_bytes(self.state.rules()))
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use crate::errors::{self, Error, Result};
+use crate::server::{Responder, ServerState};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+/// A2SServer answers A2S queries on a UDP socket.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use crowbar_a2s::server::{A2SServer, ServerState};
+/// use crowbar_a2s::types::{Info, Player, Rule};
+///
+/// struct Game;
+///
+/// impl ServerState for Game {
+///     fn info(&self) -> Info {
+///         unimplemented!()
+///     }
+///
+///     fn players(&self) -> Vec<Player> {
+///         Vec::new()
+///     }
+///
+///     fn rules(&self) -> Vec<Rule> {
+///         Vec::new()
+///     }
+/// }
+///
+/// let server = A2SServer::bind("0.0.0.0:27015", Game).unwrap();
+/// server.run().unwrap();
+/// ```
+pub struct A2SServer<S> {
+    socket: UdpSocket,
+    responder: Responder<S>,
+}
+
+impl<S: ServerState> A2SServer<S> {
+    pub fn bind<A: ToSocketAddrs>(addr: A, state: S) -> Result<Self> {
+        Ok(Self {
+            socket: UdpSocket::bind(addr)?,
+            responder: Responder::new(state),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    pub fn responder(&mut self) -> &mut Responder<S> {
+        &mut self.responder
+    }
+
+    /// Receives one datagram and answers it if it is a known request.
+    ///
+    /// Errors concerning a single client, such as sending to a spoofed
+    /// address, are returned as is, and don't mean the socket failed.
+    pub fn serve_one(&self) -> Result<()> {
+        let mut data = [0; 1400];
+        let (read, client) = self.socket.recv_from(&mut data)?;
+
+        for datagram in self.responder.respond(&data[..read], client)? {
+            self.socket.send_to(&datagram, client)?;
+        }
+
+        Ok(())
+    }
+
+    /// Answers queries until the socket fails. Responses which cannot be
+    /// encoded are dropped, and so are datagrams from or to clients which
+    /// can't be reached, see [`Self::serve_one`].
+    pub fn run(&self) -> Result<()> {
+        loop {
+            match self.serve_one() {
+                Err(Error::Io(err)) if !errors::is_transient(&err) => return Err(Error::Io(err)),
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,335 @@
+//! Server details Arma 3 and DayZ servers pack into their A2S_RULES.
+//!
+//! Rather than readable rules, these servers send binary data split into
+//! chunks, each a rule named by two bytes: the number of the chunk counting
+//! from 1, and the number of chunks. Within the data, `0x01 0x01` stands for
+//! `0x01`, `0x01 0x02` for `0x00` and `0x01 0x03` for `0xFF`, as rules can't
+//! hold NUL bytes.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use crowbar_a2s::types::arma::{ArmaRules, Game};
+//!
+//! let client = crowbar_a2s::Builder::new().build_sync().unwrap();
+//! let rules = client.rules_raw("127.0.0.1:2303").unwrap();
+//! let details = ArmaRules::from_rules(&rules, Game::Arma3).unwrap();
+//!
+//! for m in details.mods {
+//!     println!("{} ({})", m.name, m.workshop_id);
+//! }
+//! ```
+
+use crate::errors::{Error, Result};
+use crate::types::{Field, RawRule};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Cursor, Read};
+
+/// Largest chunk sent by servers.
+const CHUNK_SIZE: usize = 127;
+
+/// Bit of the mod flags marking DLCs, the lower bits hold the length of the
+/// workshop ID.
+const MOD_DLC: u8 = 0x10;
+
+/// Game whose layout the data is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Game {
+    Arma3,
+
+    /// DayZ, which leaves out the difficulty settings.
+    DayZ,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct ArmaRules {
+    /// Version of the layout, 3 as of Arma 3 1.56.
+    pub version: u8,
+
+    /// Flags set when the server left out mods or signatures for the data
+    /// to fit.
+    pub overflow: u8,
+
+    /// DLCs required by the server, one bit each.
+    pub dlc: u16,
+
+    /// Hashes of the required DLCs, from the lowest bit of `dlc` up.
+    pub dlc_hashes: Vec<u32>,
+
+    /// Difficulty settings, `None` for DayZ servers.
+    pub difficulty: Option<Difficulty>,
+
+    /// Mods loaded by the server.
+    pub mods: Vec<Mod>,
+
+    /// Names of the keys the server accepts signatures of.
+    pub signatures: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Difficulty {
+    /// Difficulty preset, from 0.
+    pub level: u8,
+
+    /// AI skill preset, from 0.
+    pub ai_level: u8,
+
+    pub advanced_flight_model: bool,
+
+    pub third_person: bool,
+
+    pub crosshair: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Mod {
+    pub hash: u32,
+
+    /// Steam Workshop ID of the mod, or app ID of the DLC.
+    pub workshop_id: u64,
+
+    /// Whether the mod is a DLC.
+    pub dlc: bool,
+
+    pub name: String,
+}
+
+impl ArmaRules {
+    /// Decodes the data the `rules` of a server hold, see [`reassemble`].
+    pub fn from_rules(rules: &[RawRule], game: Game) -> Result<Self> {
+        Self::from_bytes(&reassemble(rules)?, game)
+    }
+
+    /// Decodes the data, once reassembled and unescaped.
+    pub fn from_bytes(data: &[u8], game: Game) -> Result<Self> {
+        let mut data = Cursor::new(data);
+
+        let version = data.read_u8().field("version")?;
+        let overflow = data.read_u8().field("overflow")?;
+        let dlc = data.read_u16::<LittleEndian>().field("dlc")?;
+
+        let difficulty = match game {
+            Game::Arma3 => {
+                let flags = data.read_u8().field("difficulty")?;
+                let crosshair = data.read_u8().field("crosshair")?;
+                Some(Difficulty {
+                    level: flags & 0x07,
+                    ai_level: (flags >> 3) & 0x07,
+                    advanced_flight_model: flags & 0x40 != 0,
+                    third_person: flags & 0x80 != 0,
+                    crosshair: crosshair != 0,
+                })
+            }
+            Game::DayZ => None,
+        };
+
+        let mut dlc_hashes = Vec::with_capacity(dlc.count_ones() as usize);
+        for _ in 0..dlc.count_ones() {
+            dlc_hashes.push(data.read_u32::<LittleEndian>().field("dlc_hash")?);
+        }
+
+        let count = data.read_u8().field("mod_count")?;
+        let mut mods = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let hash = data.read_u32::<LittleEndian>().field("mod_hash")?;
+            let flags = data.read_u8().field("mod_flags")?;
+
+            let length = (flags & 0x0F) as usize;
+            if length > 8 {
+                return Err(Error::InvalidResponse).field("workshop_id");
+            }
+            let mut id = [0; 8];
+            data.read_exact(&mut id[..length]).field("workshop_id")?;
+
+            mods.push(Mod {
+                hash,
+                workshop_id: u64::from_le_bytes(id),
+                dlc: flags & MOD_DLC != 0,
+                name: read_string(&mut data).field("mod_name")?,
+            });
+        }
+
+        let count = data.read_u8().field("signature_count")?;
+        let mut signatures = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            signatures.push(read_string(&mut data).field("signature")?);
+        }
+
+        Ok(Self {
+            version,
+            overflow,
+            dlc,
+            dlc_hashes,
+            difficulty,
+            mods,
+            signatures,
+        })
+    }
+
+    /// Encodes the data, in the layout of DayZ when there are no difficulty
+    /// settings. Only the first 255 mods and signatures are kept.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![self.version, self.overflow];
+        let _ = bytes.write_u16::<LittleEndian>(self.dlc);
+
+        if let Some(difficulty) = &self.difficulty {
+            bytes.push(
+                difficulty.level & 0x07
+                    | (difficulty.ai_level & 0x07) << 3
+                    | (difficulty.advanced_flight_model as u8) << 6
+                    | (difficulty.third_person as u8) << 7,
+            );
+            bytes.push(difficulty.crosshair as u8);
+        }
+
+        for hash in &self.dlc_hashes {
+            let _ = bytes.write_u32::<LittleEndian>(*hash);
+        }
+
+        bytes.push(self.mods.len().min(u8::MAX as usize) as u8);
+        for m in self.mods.iter().take(u8::MAX as usize) {
+            let id = m.workshop_id.to_le_bytes();
+            let length = 8 - m.workshop_id.leading_zeros() as usize / 8;
+
+            let _ = bytes.write_u32::<LittleEndian>(m.hash);
+            bytes.push(length as u8 | if m.dlc { MOD_DLC } else { 0 });
+            bytes.extend(&id[..length]);
+            write_string(&mut bytes, &m.name);
+        }
+
+        bytes.push(self.signatures.len().min(u8::MAX as usize) as u8);
+        for signature in self.signatures.iter().take(u8::MAX as usize) {
+            write_string(&mut bytes, signature);
+        }
+
+        bytes
+    }
+
+    /// Escapes and splits the data into rules, as a server sends it.
+    pub fn to_rules(&self) -> Vec<RawRule> {
+        let data = escape(&self.to_bytes());
+        // Chunks are numbered with a byte
+        let chunks: Vec<&[u8]> = data.chunks(CHUNK_SIZE).take(u8::MAX as usize).collect();
+
+        chunks
+            .iter()
+            .enumerate()
+            .map(|(index, chunk)| RawRule {
+                name: vec![index as u8 + 1, chunks.len() as u8],
+                value: chunk.to_vec(),
+            })
+            .collect()
+    }
+}
+
+/// Joins the chunks found among `rules`, in order, and unescapes them. Other
+/// rules are ignored, so the data is empty if there were no chunks.
+///
+/// As other rules may have names which look like chunks, chunks are grouped
+/// by the number of chunks they announce, and the group holding the most of
+/// them is kept, preferring one which is complete.
+pub fn reassemble(rules: &[RawRule]) -> Result<Vec<u8>> {
+    let mut runs: BTreeMap<u8, Vec<Option<&[u8]>>> = BTreeMap::new();
+    let mut repeated = BTreeSet::new();
+
+    for rule in rules {
+        let (index, count) = match rule.name[..] {
+            [index, count] if index >= 1 && index <= count => (index, count),
+            _ => continue,
+        };
+
+        let chunks = runs
+            .entry(count)
+            .or_insert_with(|| vec![None; count as usize]);
+        if chunks[index as usize - 1].replace(&rule.value).is_some() {
+            repeated.insert(count);
+        }
+    }
+
+    if runs.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // A run sending a chunk twice isn't telling which one to use
+    let chunks = runs
+        .into_iter()
+        .filter(|(count, _)| !repeated.contains(count))
+        .map(|(_, chunks)| chunks)
+        .max_by_key(|chunks| {
+            let received = chunks.iter().flatten().count();
+            (received == chunks.len(), received)
+        })
+        .ok_or(Error::InvalidResponse)
+        .field("chunk")?;
+
+    let mut data = Vec::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        match chunk {
+            Some(chunk) => data.extend_from_slice(chunk),
+            None => {
+                return Err(Error::MissingChunk {
+                    index: index as u8 + 1,
+                    count: chunks.len() as u8,
+                })
+            }
+        }
+    }
+
+    unescape(&data)
+}
+
+fn unescape(data: &[u8]) -> Result<Vec<u8>> {
+    let mut unescaped = Vec::with_capacity(data.len());
+    let mut bytes = data.iter();
+
+    while let Some(&byte) = bytes.next() {
+        if byte != 0x01 {
+            unescaped.push(byte);
+            continue;
+        }
+
+        unescaped.push(match bytes.next() {
+            Some(0x01) => 0x01,
+            Some(0x02) => 0x00,
+            Some(0x03) => 0xFF,
+            _ => return Err(Error::InvalidResponse).field("escape"),
+        });
+    }
+
+    Ok(unescaped)
+}
+
+fn escape(data: &[u8]) -> Vec<u8> {
+    let mut escaped = Vec::with_capacity(data.len());
+
+    for &byte in data {
+        match byte {
+            0x01 => escaped.extend([0x01, 0x01]),
+            0x00 => escaped.extend([0x01, 0x02]),
+            0xFF => escaped.extend([0x01, 0x03]),
+            _ => escaped.push(byte),
+        }
+    }
+
+    escaped
+}
+
+/// Reads a string prefixed with its length.
+fn read_string(data: &mut Cursor<&[u8]>) -> Result<String> {
+    let length = data.read_u8()?;
+    let mut bytes = vec![0; length as usize];
+    data.read_exact(&mut bytes)?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn write_string(bytes: &mut Vec<u8>, value: &str) {
+    let value = &value.as_bytes()[..value.len().min(u8::MAX as usize)];
+    bytes.push(value.len() as u8);
+    bytes.extend(value);
+}
@@ -0,0 +1,180 @@
+//! Change sets between two responses of the same server.
+//!
+//! These are pure functions, the async `Watcher` uses them to report changes
+//! between polls.
+
+use crate::types::info::{ServerOS, ServerType};
+use crate::types::{Info, Player, Rule, Text};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Connection time a player may lose between responses and still be
+/// considered the same player, as servers round it.
+const DURATION_SLACK: f32 = 1.0;
+
+/// Value of a field before and after a change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct FieldChange<T> {
+    pub from: T,
+    pub to: T,
+}
+
+/// Field of [`Info`] which changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum InfoChange {
+    Protocol(FieldChange<u8>),
+    Name(FieldChange<Text>),
+    Map(FieldChange<Text>),
+    Folder(FieldChange<Text>),
+    Game(FieldChange<Text>),
+    AppId(FieldChange<u16>),
+    Players(FieldChange<u8>),
+    MaxPlayers(FieldChange<u8>),
+    Bots(FieldChange<u8>),
+    ServerType(FieldChange<ServerType>),
+    ServerOS(FieldChange<ServerOS>),
+    Visibility(FieldChange<bool>),
+    Vac(FieldChange<bool>),
+    Version(FieldChange<Text>),
+    Port(FieldChange<Option<u16>>),
+    SteamId(FieldChange<Option<u64>>),
+    Keywords(FieldChange<Option<Text>>),
+    GameId(FieldChange<Option<u64>>),
+}
+
+/// Player who joined or left.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum PlayerChange {
+    /// The player as of the new response
+    Joined(Player),
+    /// The player as of the old response
+    Left(Player),
+}
+
+/// Rule which was added, removed or changed value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum RuleChange {
+    Added(Rule),
+    Removed(Rule),
+    Modified {
+        name: Text,
+        value: FieldChange<Text>,
+    },
+}
+
+/// Fields which differ between `old` and `new`, in declaration order.
+pub fn info(old: &Info, new: &Info) -> Vec<InfoChange> {
+    let mut changes = Vec::new();
+
+    macro_rules! field {
+        ($variant:ident, $($field:ident).+) => {
+            if old.$($field).+ != new.$($field).+ {
+                changes.push(InfoChange::$variant(FieldChange {
+                    from: old.$($field).+.clone(),
+                    to: new.$($field).+.clone(),
+                }));
+            }
+        };
+    }
+
+    field!(Protocol, protocol);
+    field!(Name, name);
+    field!(Map, map);
+    field!(Folder, folder);
+    field!(Game, game);
+    field!(AppId, app_id);
+    field!(Players, players);
+    field!(MaxPlayers, max_players);
+    field!(Bots, bots);
+    field!(ServerType, server_type);
+    field!(ServerOS, server_os);
+    field!(Visibility, visibility);
+    field!(Vac, vac);
+    field!(Version, version);
+    field!(Port, extended_server_info.port);
+    field!(SteamId, extended_server_info.steam_id);
+    field!(Keywords, extended_server_info.keywords);
+    field!(GameId, extended_server_info.game_id);
+
+    changes
+}
+
+/// Players who left, in `old` order, then players who joined, in `new`
+/// order.
+///
+/// Players only have a name and a connection time to tell them apart: a
+/// player is the same if the name matches and the connection time didn't go
+/// backwards. Among players sharing a name, the longest connected players
+/// are matched first, so a player who reconnected is reported as having left
+/// and joined again.
+pub fn players(old: &[Player], new: &[Player]) -> Vec<PlayerChange> {
+    let mut matched_old = vec![false; old.len()];
+    let mut matched_new = vec![false; new.len()];
+
+    let mut by_duration: Vec<usize> = (0..new.len()).collect();
+    by_duration.sort_by(|&a, &b| new[b].duration.total_cmp(&new[a].duration));
+
+    for index in by_duration {
+        let player = &new[index];
+        let same = old
+            .iter()
+            .enumerate()
+            .filter(|(i, p)| {
+                !matched_old[*i]
+                    && p.name == player.name
+                    && p.duration <= player.duration + DURATION_SLACK
+            })
+            .max_by(|(_, a), (_, b)| a.duration.total_cmp(&b.duration))
+            .map(|(i, _)| i);
+
+        if let Some(same) = same {
+            matched_old[same] = true;
+            matched_new[index] = true;
+        }
+    }
+
+    let left = old
+        .iter()
+        .zip(matched_old)
+        .filter(|(_, matched)| !matched)
+        .map(|(player, _)| PlayerChange::Left(player.clone()));
+    let joined = new
+        .iter()
+        .zip(matched_new)
+        .filter(|(_, matched)| !matched)
+        .map(|(player, _)| PlayerChange::Joined(player.clone()));
+
+    left.chain(joined).collect()
+}
+
+/// Rules removed or modified, in `old` order, then rules added, in `new`
+/// order. Rules are matched by name.
+pub fn rules(old: &[Rule], new: &[Rule]) -> Vec<RuleChange> {
+    let mut changes = Vec::new();
+
+    for rule in old {
+        match new.iter().find(|r| r.name == rule.name) {
+            None => changes.push(RuleChange::Removed(rule.clone())),
+            Some(r) if r.value != rule.value => changes.push(RuleChange::Modified {
+                name: rule.name.clone(),
+                value: FieldChange {
+                    from: rule.value.clone(),
+                    to: r.value.clone(),
+                },
+            }),
+            Some(_) => {}
+        }
+    }
+
+    for rule in new {
+        if old.iter().all(|r| r.name != rule.name) {
+            changes.push(RuleChange::Added(rule.clone()));
+        }
+    }
+
+    changes
+}
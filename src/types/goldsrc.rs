@@ -0,0 +1,215 @@
+use crate::errors::{Error, Result};
+use crate::types::info::{Info, ServerOS, ServerType};
+use crate::types::{read_header, Field, ReadCString, Text};
+use byteorder::{LittleEndian, ReadBytesExt};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::io::Cursor;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct GoldSrcMod {
+    /// URL to mod website.
+    pub link: Text,
+
+    /// URL to download the mod.
+    pub download_link: Text,
+
+    /// Version of mod installed on server.
+    pub version: u32,
+
+    /// Space (in bytes) the mod takes up.
+    pub size: u32,
+
+    /// Indicates whether the mod is multiplayer only.
+    pub multiplayer_only: bool,
+
+    /// Indicates whether the mod uses its own DLL rather than the Half-Life DLL.
+    pub own_dll: bool,
+}
+
+/// Obsolete GoldSrc response to A2S_INFO, still sent by some HLDS servers.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct GoldSrcInfo {
+    /// IP address and port of the server.
+    pub address: Text,
+
+    /// Name of the server.
+    pub name: Text,
+
+    /// Map the server has currently loaded.
+    pub map: Text,
+
+    /// Name of the folder containing the game files.
+    pub folder: Text,
+
+    /// Full name of the game.
+    pub game: Text,
+
+    /// Number of players on the server.
+    pub players: u8,
+
+    /// Maximum number of players the server reports it can hold.
+    pub max_players: u8,
+
+    /// Protocol version used by the server.
+    pub protocol: u8,
+
+    /// Indicates the type of server
+    pub server_type: ServerType,
+
+    /// Indicates the operating system of the server
+    pub server_os: ServerOS,
+
+    /// Indicates whether the server requires a password
+    pub visibility: bool,
+
+    /// Present if the game is a mod of Half-Life
+    pub mod_info: Option<GoldSrcMod>,
+
+    /// Specifies whether the server uses VAC
+    pub vac: bool,
+
+    /// Number of bots on the server.
+    pub bots: u8,
+}
+
+impl GoldSrcInfo {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend(&[0xff, 0xff, 0xff, 0xff, 0x6d]);
+        bytes.extend(self.address.as_bytes());
+        bytes.push(0);
+        bytes.extend(self.name.as_bytes());
+        bytes.push(0);
+        bytes.extend(self.map.as_bytes());
+        bytes.push(0);
+        bytes.extend(self.folder.as_bytes());
+        bytes.push(0);
+        bytes.extend(self.game.as_bytes());
+        bytes.push(0);
+        bytes.push(self.players);
+        bytes.push(self.max_players);
+        bytes.push(self.protocol);
+        bytes.push(match self.server_type {
+            ServerType::Dedicated => b'D',
+            ServerType::NonDedicated => b'L',
+            ServerType::SourceTV => b'P',
+            ServerType::Unknown(value) => value,
+        });
+        bytes.push(match self.server_os {
+            ServerOS::Linux => b'L',
+            ServerOS::Windows => b'W',
+            ServerOS::Mac => b'm',
+            ServerOS::Unknown(value) => value,
+        });
+        bytes.push(if self.visibility { 1 } else { 0 });
+
+        if let Some(mod_info) = &self.mod_info {
+            bytes.push(1);
+            bytes.extend(mod_info.link.as_bytes());
+            bytes.push(0);
+            bytes.extend(mod_info.download_link.as_bytes());
+            bytes.push(0);
+            bytes.push(0);
+            bytes.extend(mod_info.version.to_le_bytes());
+            bytes.extend(mod_info.size.to_le_bytes());
+            bytes.push(if mod_info.multiplayer_only { 1 } else { 0 });
+            bytes.push(if mod_info.own_dll { 1 } else { 0 });
+        } else {
+            bytes.push(0);
+        }
+
+        bytes.push(if self.vac { 1 } else { 0 });
+        bytes.push(self.bots);
+
+        bytes
+    }
+
+    pub fn from_cursor(mut data: Cursor<Vec<u8>>) -> Result<Self> {
+        read_header(&mut data, 0x6D)?;
+
+        let address = data.read_text().field("address")?;
+        let name = data.read_text().field("name")?;
+        let map = data.read_text().field("map")?;
+        let folder = data.read_text().field("folder")?;
+        let game = data.read_text().field("game")?;
+        let players = data.read_u8().field("players")?;
+        let max_players = data.read_u8().field("max_players")?;
+        let protocol = data.read_u8().field("protocol")?;
+        let server_type = data
+            .read_u8()
+            .map_err(Error::from)
+            .and_then(ServerType::try_from)
+            .field("server_type")?;
+        let server_os = data
+            .read_u8()
+            .map_err(Error::from)
+            .and_then(ServerOS::try_from)
+            .field("server_os")?;
+        let visibility = data.read_u8().field("visibility")? != 0;
+        let mod_info = if data.read_u8().field("mod")? != 0 {
+            let link = data.read_text().field("mod.link")?;
+            let download_link = data.read_text().field("mod.download_link")?;
+            // NULL byte
+            data.read_u8().field("mod")?;
+            Some(GoldSrcMod {
+                link,
+                download_link,
+                version: data.read_u32::<LittleEndian>().field("mod.version")?,
+                size: data.read_u32::<LittleEndian>().field("mod.size")?,
+                multiplayer_only: data.read_u8().field("mod.multiplayer_only")? != 0,
+                own_dll: data.read_u8().field("mod.own_dll")? != 0,
+            })
+        } else {
+            None
+        };
+        let vac = data.read_u8().field("vac")? != 0;
+        let bots = data.read_u8().field("bots")?;
+
+        Ok(GoldSrcInfo {
+            address,
+            name,
+            map,
+            folder,
+            game,
+            players,
+            max_players,
+            protocol,
+            server_type,
+            server_os,
+            visibility,
+            mod_info,
+            vac,
+            bots,
+        })
+    }
+}
+
+/// Response to A2S_INFO from either engine generation.
+///
+/// HLDS servers may answer with the obsolete GoldSrc format, while Source
+/// servers (and up to date HLDS servers) answer with the Source format.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum ServerInfo {
+    Source(Info),
+    GoldSrc(GoldSrcInfo),
+}
+
+impl ServerInfo {
+    /// Parses either response format, detected from the header byte.
+    pub fn from_cursor(data: Cursor<Vec<u8>>) -> Result<Self> {
+        match data.get_ref().get(data.position() as usize) {
+            Some(0x49) => Ok(Self::Source(Info::from_cursor(data)?)),
+            Some(0x6D) => Ok(Self::GoldSrc(GoldSrcInfo::from_cursor(data)?)),
+            Some(&found) => Err(Error::UnexpectedHeader {
+                expected: 0x49,
+                found,
+            }),
+            None => Err(Error::InvalidResponse),
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use crate::errors::{Error, Result};
-use crate::types::ReadCString;
+use crate::types::lenient::{Leniency, Parsed};
+use crate::types::{read_header, Field, Text};
 use byteorder::{LittleEndian, ReadBytesExt};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -64,7 +65,7 @@ pub struct ExtendedServerInfo {
 
     /// Tags that describe the game according to the server (for future use.)
     /// Available if edf & 0x20 is true
-    pub keywords: Option<String>,
+    pub keywords: Option<Text>,
 
     /// The server's 64-bit GameID. If this is present, a more accurate AppID is present in the low 24 bits.
     /// The earlier AppID could have been truncated as it was forced into 16-bit storage.
@@ -79,37 +80,61 @@ pub struct SourceTVInfo {
     pub port: u16,
 
     /// Name of the spectator server for SourceTV.
-    pub name: String,
+    pub name: Text,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
-#[repr(u8)]
 pub enum ServerType {
-    Dedicated = b'd',
-    NonDedicated = b'i',
-    SourceTV = b'p',
+    Dedicated,
+    NonDedicated,
+    SourceTV,
+    /// Kept by lenient parsing, see [`Info::from_cursor_lenient`]
+    Unknown(u8),
+}
+
+impl From<ServerType> for u8 {
+    fn from(server_type: ServerType) -> Self {
+        match server_type {
+            ServerType::Dedicated => b'd',
+            ServerType::NonDedicated => b'i',
+            ServerType::SourceTV => b'p',
+            ServerType::Unknown(value) => value,
+        }
+    }
 }
 
 impl TryFrom<u8> for ServerType {
     type Error = Error;
     fn try_from(val: u8) -> Result<Self> {
         match val {
-            b'd' => Ok(Self::Dedicated),
-            b'i' => Ok(Self::NonDedicated),
-            b'p' => Ok(Self::SourceTV),
+            b'd' | b'D' => Ok(Self::Dedicated),
+            b'i' | b'l' | b'L' => Ok(Self::NonDedicated),
+            b'p' | b'P' => Ok(Self::SourceTV),
             _ => Err(Self::Error::Other("Invalid server type")),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
-#[repr(u8)]
 pub enum ServerOS {
-    Linux = b'l',
-    Windows = b'w',
-    Mac = b'm',
+    Linux,
+    Windows,
+    Mac,
+    /// Kept by lenient parsing, see [`Info::from_cursor_lenient`]
+    Unknown(u8),
+}
+
+impl From<ServerOS> for u8 {
+    fn from(server_os: ServerOS) -> Self {
+        match server_os {
+            ServerOS::Linux => b'l',
+            ServerOS::Windows => b'w',
+            ServerOS::Mac => b'm',
+            ServerOS::Unknown(value) => value,
+        }
+    }
 }
 
 impl TryFrom<u8> for ServerOS {
@@ -117,8 +142,8 @@ impl TryFrom<u8> for ServerOS {
 
     fn try_from(val: u8) -> Result<Self> {
         match val {
-            b'l' => Ok(Self::Linux),
-            b'w' => Ok(Self::Windows),
+            b'l' | b'L' => Ok(Self::Linux),
+            b'w' | b'W' => Ok(Self::Windows),
             b'm' | b'o' => Ok(Self::Mac),
             _ => Err(Self::Error::Other("Invalid environment")),
         }
@@ -132,16 +157,16 @@ pub struct Info {
     pub protocol: u8,
 
     /// Name of the server.
-    pub name: String,
+    pub name: Text,
 
     /// Map the server has currently loaded.
-    pub map: String,
+    pub map: Text,
 
     /// Name of the folder containing the game files.
-    pub folder: String,
+    pub folder: Text,
 
     /// Full name of the game.
-    pub game: String,
+    pub game: Text,
 
     /// Steam Application ID of game.
     pub app_id: u16,
@@ -172,7 +197,7 @@ pub struct Info {
     pub the_ship: Option<TheShip>,
 
     /// Version of the game installed on the server.
-    pub version: String,
+    pub version: Text,
 
     /// If present, this specifies which additional data fields will be included.
     pub edf: u8,
@@ -200,8 +225,8 @@ impl Info {
         bytes.push(self.players);
         bytes.push(self.max_players);
         bytes.push(self.bots);
-        bytes.push(self.server_type as u8);
-        bytes.push(self.server_os as u8);
+        bytes.push(self.server_type.into());
+        bytes.push(self.server_os.into());
         bytes.push(if self.visibility { 1 } else { 0 });
         bytes.push(if self.vac { 1 } else { 0 });
 
@@ -241,34 +266,57 @@ impl Info {
         bytes
     }
 
-    pub fn from_cursor(mut data: Cursor<Vec<u8>>) -> Result<Self> {
-        if data.read_u8()? != 0x49u8 {
-            return Err(Error::InvalidResponse);
-        }
+    pub fn from_cursor(data: Cursor<Vec<u8>>) -> Result<Self> {
+        Self::parse(data, &mut Leniency::new(false))
+    }
+
+    /// Parses the response leniently: unknown server types and
+    /// environments are kept as `Unknown`, and fields from the version on
+    /// are left out if the response ends early.
+    pub fn from_cursor_lenient(data: Cursor<Vec<u8>>) -> Result<Parsed<Self>> {
+        let mut leniency = Leniency::new(true);
+        let info = Self::parse(data, &mut leniency)?;
+        Ok(leniency.finish(info))
+    }
 
-        let protocol = data.read_u8()?;
-        let name = data.read_cstring()?;
-        let map = data.read_cstring()?;
-        let folder = data.read_cstring()?;
-        let game = data.read_cstring()?;
-        let app_id = data.read_u16::<LittleEndian>()?;
-        let players = data.read_u8()?;
-        let max_players = data.read_u8()?;
-        let bots = data.read_u8()?;
-        let server_type = ServerType::try_from(data.read_u8()?)?;
-        let server_os = ServerOS::try_from(data.read_u8()?)?;
-        let visibility = data.read_u8()? != 0;
-        let vac = data.read_u8()? != 0;
+    fn parse(mut data: Cursor<Vec<u8>>, leniency: &mut Leniency) -> Result<Self> {
+        read_header(&mut data, 0x49)?;
+
+        let protocol = data.read_u8().field("protocol")?;
+        let name = leniency.text(&mut data).field("name")?;
+        let map = leniency.text(&mut data).field("map")?;
+        let folder = leniency.text(&mut data).field("folder")?;
+        let game = leniency.text(&mut data).field("game")?;
+        let app_id = data.read_u16::<LittleEndian>().field("app_id")?;
+        let players = data.read_u8().field("players")?;
+        let max_players = data.read_u8().field("max_players")?;
+        let bots = data.read_u8().field("bots")?;
+        let server_type = leniency.value(
+            "server_type",
+            data.read_u8().field("server_type")?,
+            ServerType::try_from,
+            ServerType::Unknown,
+        )?;
+        let server_os = leniency.value(
+            "server_os",
+            data.read_u8().field("server_os")?,
+            ServerOS::try_from,
+            ServerOS::Unknown,
+        )?;
+        let visibility = data.read_u8().field("visibility")? != 0;
+        let vac = data.read_u8().field("vac")? != 0;
         let the_ship = if app_id == 2400 {
             Some(TheShip {
-                mode: TheShipMode::from(data.read_u8()?),
-                witnesses: data.read_u8()?,
-                duration: data.read_u8()?,
+                mode: TheShipMode::from(data.read_u8().field("mode")?),
+                witnesses: data.read_u8().field("witnesses")?,
+                duration: data.read_u8().field("duration")?,
             })
         } else {
             None
         };
-        let version = data.read_cstring()?;
+        let version = leniency
+            .truncated(leniency.text(&mut data).field("version"))?
+            .unwrap_or_default();
         let edf = match data.read_u8() {
             Ok(val) => val,
             Err(err) => {
@@ -281,31 +329,32 @@ impl Info {
         };
         let extended_server_info = ExtendedServerInfo {
             port: if edf & 0x80 != 0 {
-                Some(data.read_u16::<LittleEndian>()?)
+                leniency.truncated(data.read_u16::<LittleEndian>().field("port"))?
             } else {
                 None
             },
             steam_id: if edf & 0x10 != 0 {
-                Some(data.read_u64::<LittleEndian>()?)
+                leniency.truncated(data.read_u64::<LittleEndian>().field("steam_id"))?
             } else {
                 None
             },
             keywords: if edf & 0x20 != 0 {
-                Some(data.read_cstring()?)
+                leniency.truncated(leniency.text(&mut data).field("keywords"))?
             } else {
                 None
             },
             game_id: if edf & 0x01 != 0 {
-                Some(data.read_u64::<LittleEndian>()?)
+                leniency.truncated(data.read_u64::<LittleEndian>().field("game_id"))?
             } else {
                 None
             },
         };
         let source_tv = if edf & 0x40 != 0 {
-            Some(SourceTVInfo {
-                port: data.read_u16::<LittleEndian>()?,
-                name: data.read_cstring()?,
-            })
+            let port =
+                leniency.truncated(data.read_u16::<LittleEndian>().field("source_tv.port"))?;
+            let name = leniency.truncated(leniency.text(&mut data).field("source_tv.name"))?;
+            port.zip(name)
+                .map(|(port, name)| SourceTVInfo { port, name })
         } else {
             None
         };
@@ -0,0 +1,200 @@
+use crate::errors::{Error, Result};
+use crate::types::{Field, ReadCString, Text};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::io::{Cursor, ErrorKind};
+
+/// A response parsed leniently, along with what was off about it.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Parsed<T> {
+    pub value: T,
+    /// Whether the response ended within an entry or a field
+    pub truncated: bool,
+    /// Number of players or rules announced by the response, which servers
+    /// don't always get right. The entries actually sent are all read.
+    pub declared: Option<usize>,
+    pub warnings: Vec<Warning>,
+}
+
+/// Players or rules parsed strictly, along with the count the response
+/// announced.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Counted<T> {
+    pub entries: Vec<T>,
+    /// Number of entries announced by the response, which may differ from
+    /// the entries it held.
+    pub declared: usize,
+}
+
+impl<T> Counted<T> {
+    /// Number of entries the response held.
+    pub fn actual(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+/// Something off about a response, which lenient parsing went past.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum Warning {
+    /// `value` isn't a known value of `field`, and was kept as `Unknown`.
+    UnknownValue { field: &'static str, value: u8 },
+
+    /// The response ended within `field`, which was left out along with
+    /// everything after it.
+    Truncated { field: &'static str },
+
+    /// The response announced `declared` players or rules, but held
+    /// `actual`.
+    CountMismatch { declared: usize, actual: usize },
+}
+
+/// Collects warnings while parsing leniently, or turns them into errors
+/// while parsing strictly.
+pub(crate) struct Leniency {
+    lenient: bool,
+    /// Whether strings have to be NUL terminated
+    terminated: bool,
+    truncated: bool,
+    declared: Option<usize>,
+    warnings: Vec<Warning>,
+}
+
+impl Leniency {
+    pub(crate) fn new(lenient: bool) -> Self {
+        Self {
+            lenient,
+            terminated: lenient,
+            truncated: false,
+            declared: None,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Whether the response ended, or was cut short.
+    pub(crate) fn at_end(&self, data: &Cursor<Vec<u8>>) -> bool {
+        self.truncated || data.position() >= data.get_ref().len() as u64
+    }
+
+    /// Reads entries with `read` up to the end of the response, along with
+    /// the `declared` number of them.
+    pub(crate) fn entries<T>(
+        &mut self,
+        data: &mut Cursor<Vec<u8>>,
+        declared: usize,
+        mut read: impl FnMut(&mut Cursor<Vec<u8>>, &Self) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        let mut entries = Vec::with_capacity(declared);
+
+        while !self.at_end(data) {
+            // Servers may pad the response past the announced entries
+            let rest = &data.get_ref()[data.position() as usize..];
+            if entries.len() >= declared && rest.iter().all(|&byte| byte == 0) {
+                break;
+            }
+
+            // Past the announced entries, strict parsing only keeps whole
+            // ones
+            let extra = !self.lenient && entries.len() >= declared;
+            self.terminated = self.lenient || extra;
+
+            match read(data, self) {
+                Err(Error::InvalidField { source, .. }) if extra && is_eof(&source) => break,
+                result => {
+                    if let Some(entry) = self.truncated(result)? {
+                        entries.push(entry);
+                    }
+                }
+            }
+        }
+        self.count(declared, entries.len());
+
+        Ok(entries)
+    }
+
+    /// Records the number of entries the response announced and held. Cut
+    /// short responses are already warned about.
+    fn count(&mut self, declared: usize, actual: usize) {
+        self.declared = Some(declared);
+        if self.lenient && !self.truncated && declared != actual {
+            self.warnings
+                .push(Warning::CountMismatch { declared, actual });
+        }
+    }
+
+    /// Reads a NUL terminated string. When parsing leniently, a string the
+    /// response ends within is an unexpected end, for the response to be
+    /// reported as truncated; strict parsing reads it up to the end, unless
+    /// it's past the announced entries.
+    pub(crate) fn cbytes(&self, data: &mut Cursor<Vec<u8>>) -> Result<Vec<u8>> {
+        let start = data.position();
+        let bytes = data.read_cbytes()?;
+        if self.terminated && data.position() == start + bytes.len() as u64 {
+            return Err(std::io::Error::from(ErrorKind::UnexpectedEof).into());
+        }
+        Ok(bytes)
+    }
+
+    pub(crate) fn text(&self, data: &mut Cursor<Vec<u8>>) -> Result<Text> {
+        self.cbytes(data).map(Text::new)
+    }
+
+    /// Parses the `value` of `field` with `parse`, keeping values it doesn't
+    /// know as `unknown`.
+    pub(crate) fn value<T>(
+        &mut self,
+        field: &'static str,
+        value: u8,
+        parse: impl FnOnce(u8) -> Result<T>,
+        unknown: impl FnOnce(u8) -> T,
+    ) -> Result<T> {
+        match parse(value) {
+            Ok(parsed) => Ok(parsed),
+            Err(_) if self.lenient => {
+                self.warnings.push(Warning::UnknownValue { field, value });
+                Ok(unknown(value))
+            }
+            Err(err) => Err(err).field(field),
+        }
+    }
+
+    /// Result of reading a field the response may end before, `None` once
+    /// it did.
+    pub(crate) fn truncated<T>(&mut self, result: Result<T>) -> Result<Option<T>> {
+        if self.truncated {
+            return Ok(None);
+        }
+
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(Error::InvalidField { field, source }) if self.lenient && is_eof(&source) => {
+                self.truncated = true;
+                self.warnings.push(Warning::Truncated { field });
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    pub(crate) fn counted<T>(self, entries: Vec<T>) -> Counted<T> {
+        Counted {
+            entries,
+            declared: self.declared.unwrap_or_default(),
+        }
+    }
+
+    pub(crate) fn finish<T>(self, value: T) -> Parsed<T> {
+        Parsed {
+            value,
+            truncated: self.truncated,
+            declared: self.declared,
+            warnings: self.warnings,
+        }
+    }
+}
+
+fn is_eof(err: &Error) -> bool {
+    matches!(err, Error::Io(err) if err.kind() == ErrorKind::UnexpectedEof)
+}
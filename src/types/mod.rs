@@ -1,23 +1,54 @@
+pub mod arma;
+
+pub mod diff;
+
+pub mod goldsrc;
+
 pub mod info;
 
+pub mod lenient;
+
+pub mod ping;
+
 pub mod players;
 
 pub mod rules;
-use crate::errors::Result;
+
+pub mod snapshot;
+
+pub mod text;
+use crate::errors::{Error, Result};
+use byteorder::ReadBytesExt;
 use std::io::{Cursor, Read};
 
+pub use crate::types::goldsrc::{GoldSrcInfo, GoldSrcMod, ServerInfo};
+
 pub use crate::types::info::{ExtendedServerInfo, Info, INFO_REQUEST};
 
+pub use crate::types::lenient::{Counted, Parsed, Warning};
+
+pub use crate::types::ping::{Timed, PING_REQUEST, PING_RESPONSE};
+
 pub use crate::types::players::{Player, TheShipPlayer, PLAYER_REQUEST};
 
-pub use crate::types::rules::{Rule, RULES_REQUEST};
+pub use crate::types::rules::{RawRule, Rule, RULES_REQUEST};
+
+pub use crate::types::snapshot::ServerSnapshot;
+
+pub use crate::types::text::Text;
 
 trait ReadCString {
-    fn read_cstring(&mut self) -> Result<String>;
+    /// Reads a NUL terminated string, as is. A string the response ends
+    /// within is read up to the end.
+    fn read_cbytes(&mut self) -> Result<Vec<u8>>;
+
+    fn read_text(&mut self) -> Result<Text> {
+        self.read_cbytes().map(Text::new)
+    }
 }
 
 impl ReadCString for Cursor<Vec<u8>> {
-    fn read_cstring(&mut self) -> Result<String> {
+    fn read_cbytes(&mut self) -> Result<Vec<u8>> {
         let end = self.get_ref().len() as u64;
         let mut buf = [0; 1];
         let mut str_vec = Vec::with_capacity(256);
@@ -29,6 +60,29 @@ impl ReadCString for Cursor<Vec<u8>> {
                 str_vec.push(buf[0]);
             }
         }
-        Ok(String::from_utf8_lossy(&str_vec[..]).into_owned())
+        Ok(str_vec)
+    }
+}
+
+/// Names the response field a failed read was for.
+trait Field<T> {
+    fn field(self, name: &'static str) -> Result<T>;
+}
+
+impl<T, E: Into<Error>> Field<T> for std::result::Result<T, E> {
+    fn field(self, name: &'static str) -> Result<T> {
+        self.map_err(|err| Error::InvalidField {
+            field: name,
+            source: Box::new(err.into()),
+        })
+    }
+}
+
+/// Reads the response type byte, which must be `expected`.
+pub(crate) fn read_header(data: &mut Cursor<Vec<u8>>, expected: u8) -> Result<()> {
+    let found = data.read_u8().field("header")?;
+    if found != expected {
+        return Err(Error::UnexpectedHeader { expected, found });
     }
+    Ok(())
 }
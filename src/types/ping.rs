@@ -0,0 +1,19 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub const PING_REQUEST: [u8; 5] = [0xFF, 0xFF, 0xFF, 0xFF, 0x69];
+
+/// Header of the A2A_PING response
+pub const PING_RESPONSE: u8 = 0x6A;
+
+/// A response along with the round-trip time it took.
+///
+/// The round-trip time is measured from sending the last request, after any
+/// challenge, to receiving the first datagram of the response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Timed<T> {
+    pub value: T,
+    pub rtt: Duration,
+}
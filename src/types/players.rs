@@ -1,5 +1,6 @@
-use crate::errors::{Error, Result};
-use crate::types::ReadCString;
+use crate::errors::Result;
+use crate::types::lenient::{Counted, Leniency, Parsed};
+use crate::types::{read_header, Field, Text};
 use byteorder::{LittleEndian, ReadBytesExt};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -15,7 +16,7 @@ pub struct Player {
     pub index: u8,
 
     /// Name of the player.
-    pub name: String,
+    pub name: Text,
 
     /// Player's score (usually "frags" or "kills".)
     pub score: i32,
@@ -36,34 +37,85 @@ pub struct TheShipPlayer {
 }
 
 impl Player {
-    pub fn from_cursor(mut data: Cursor<Vec<u8>>, app_id: u16) -> Result<Vec<Self>> {
-        if data.read_u8()? != 0x44 {
-            return Err(Error::InvalidResponse);
+    pub fn vec_to_bytes(players: Vec<Self>) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        bytes.extend(&[0xff, 0xff, 0xff, 0xff, 0x44]);
+
+        // Clients read every entry sent, whatever the count says
+        bytes.push(players.len().min(u8::MAX as usize) as u8);
+
+        for player in players {
+            bytes.extend(player.to_bytes());
         }
 
-        let player_count = data.read_u8()?;
-
-        let mut players: Vec<Self> = Vec::with_capacity(player_count as usize);
-
-        for _ in 0..player_count {
-            players.push(Self {
-                index: data.read_u8()?,
-                name: data.read_cstring()?,
-                score: data.read_i32::<LittleEndian>()?,
-                duration: data.read_f32::<LittleEndian>()?,
-                the_ship: {
-                    if app_id == 2400 {
-                        Some(TheShipPlayer {
-                            deaths: data.read_u32::<LittleEndian>()?,
-                            money: data.read_u32::<LittleEndian>()?,
-                        })
-                    } else {
-                        None
-                    }
-                },
-            })
+        bytes
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        bytes.push(self.index);
+        bytes.extend(self.name.as_bytes());
+        bytes.push(0);
+        bytes.extend(self.score.to_le_bytes());
+        bytes.extend(self.duration.to_le_bytes());
+
+        if let Some(the_ship) = &self.the_ship {
+            bytes.extend(the_ship.deaths.to_le_bytes());
+            bytes.extend(the_ship.money.to_le_bytes());
         }
 
-        Ok(players)
+        bytes
+    }
+
+    pub fn from_cursor(data: Cursor<Vec<u8>>, app_id: u16) -> Result<Vec<Self>> {
+        Self::parse(data, app_id, &mut Leniency::new(false))
+    }
+
+    /// Parses the response, along with the player count it announced.
+    pub fn from_cursor_counted(data: Cursor<Vec<u8>>, app_id: u16) -> Result<Counted<Self>> {
+        let mut leniency = Leniency::new(false);
+        let players = Self::parse(data, app_id, &mut leniency)?;
+        Ok(leniency.counted(players))
+    }
+
+    /// Parses the response leniently, keeping the players read before it
+    /// ended if it's cut short, along with the player count it announced.
+    pub fn from_cursor_lenient(data: Cursor<Vec<u8>>, app_id: u16) -> Result<Parsed<Vec<Self>>> {
+        let mut leniency = Leniency::new(true);
+        let players = Self::parse(data, app_id, &mut leniency)?;
+        Ok(leniency.finish(players))
+    }
+
+    fn parse(mut data: Cursor<Vec<u8>>, app_id: u16, leniency: &mut Leniency) -> Result<Vec<Self>> {
+        read_header(&mut data, 0x44)?;
+
+        let player_count = data.read_u8().field("player_count")?;
+
+        // The count is capped at 255 and servers may leave out players, so
+        // entries are read up to the end of the response instead
+        leniency.entries(&mut data, player_count as usize, |data, leniency| {
+            Self::read(data, app_id, leniency)
+        })
+    }
+
+    fn read(data: &mut Cursor<Vec<u8>>, app_id: u16, leniency: &Leniency) -> Result<Self> {
+        Ok(Self {
+            index: data.read_u8().field("index")?,
+            name: leniency.text(data).field("name")?,
+            score: data.read_i32::<LittleEndian>().field("score")?,
+            duration: data.read_f32::<LittleEndian>().field("duration")?,
+            the_ship: {
+                if app_id == 2400 {
+                    Some(TheShipPlayer {
+                        deaths: data.read_u32::<LittleEndian>().field("deaths")?,
+                        money: data.read_u32::<LittleEndian>().field("money")?,
+                    })
+                } else {
+                    None
+                }
+            },
+        })
     }
 }
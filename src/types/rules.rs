@@ -1,5 +1,6 @@
-use crate::errors::{Error, Result};
-use crate::types::ReadCString;
+use crate::errors::Result;
+use crate::types::lenient::{Counted, Leniency, Parsed};
+use crate::types::{read_header, Field, Text};
 use byteorder::{LittleEndian, ReadBytesExt};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -7,14 +8,58 @@ use std::io::Cursor;
 
 pub const RULES_REQUEST: [u8; 5] = [0xFF, 0xFF, 0xFF, 0xFF, 0x56];
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct Rule {
     /// Name of the rule.
-    pub name: String,
+    pub name: Text,
 
     /// Value of the rule.
-    pub value: String,
+    pub value: Text,
+}
+
+/// Rule holding binary data rather than text, such as those of Arma 3 and
+/// DayZ servers, see [`arma`](crate::types::arma). Unlike [`Text`], the
+/// bytes are serialized as is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct RawRule {
+    pub name: Vec<u8>,
+    pub value: Vec<u8>,
+}
+
+impl From<RawRule> for Rule {
+    fn from(rule: RawRule) -> Self {
+        Rule {
+            name: rule.name.into(),
+            value: rule.value.into(),
+        }
+    }
+}
+
+impl RawRule {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        bytes.extend(&self.name);
+        bytes.push(0);
+        bytes.extend(&self.value);
+        bytes.push(0);
+
+        bytes
+    }
+
+    /// Parses the response into rules of binary data.
+    pub fn from_cursor(data: Cursor<Vec<u8>>) -> Result<Vec<Self>> {
+        parse(data, &mut Leniency::new(false), Self::read)
+    }
+
+    fn read(data: &mut Cursor<Vec<u8>>, leniency: &Leniency) -> Result<Self> {
+        Ok(RawRule {
+            name: leniency.cbytes(data).field("name")?,
+            value: leniency.cbytes(data).field("value")?,
+        })
+    }
 }
 
 impl Rule {
@@ -23,7 +68,8 @@ impl Rule {
 
         bytes.extend(&[0xff, 0xff, 0xff, 0xff, 0x45]);
 
-        bytes.extend(rules.len().to_le_bytes());
+        // Clients read every entry sent, whatever the count says
+        bytes.extend((rules.len().min(u16::MAX as usize) as u16).to_le_bytes());
 
         for rule in rules {
             bytes.extend(rule.to_bytes());
@@ -43,22 +89,43 @@ impl Rule {
         bytes
     }
 
-    pub fn from_cursor(mut data: Cursor<Vec<u8>>) -> Result<Vec<Self>> {
-        if data.read_u8()? != 0x45 {
-            return Err(Error::InvalidResponse);
-        }
-
-        let count = data.read_u16::<LittleEndian>()?;
+    pub fn from_cursor(data: Cursor<Vec<u8>>) -> Result<Vec<Self>> {
+        parse(data, &mut Leniency::new(false), Self::read)
+    }
 
-        let mut rules: Vec<Rule> = Vec::with_capacity(count as usize);
+    /// Parses the response, along with the rule count it announced.
+    pub fn from_cursor_counted(data: Cursor<Vec<u8>>) -> Result<Counted<Self>> {
+        let mut leniency = Leniency::new(false);
+        let rules = parse(data, &mut leniency, Self::read)?;
+        Ok(leniency.counted(rules))
+    }
 
-        for _ in 0..count {
-            rules.push(Rule {
-                name: data.read_cstring()?,
-                value: data.read_cstring()?,
-            })
-        }
+    /// Parses the response leniently, keeping the rules read before it
+    /// ended if it's cut short, along with the rule count it announced.
+    pub fn from_cursor_lenient(data: Cursor<Vec<u8>>) -> Result<Parsed<Vec<Self>>> {
+        let mut leniency = Leniency::new(true);
+        let rules = parse(data, &mut leniency, Self::read)?;
+        Ok(leniency.finish(rules))
+    }
 
-        Ok(rules)
+    fn read(data: &mut Cursor<Vec<u8>>, leniency: &Leniency) -> Result<Self> {
+        Ok(Rule {
+            name: leniency.text(data).field("name")?,
+            value: leniency.text(data).field("value")?,
+        })
     }
 }
+
+fn parse<T>(
+    mut data: Cursor<Vec<u8>>,
+    leniency: &mut Leniency,
+    read: fn(&mut Cursor<Vec<u8>>, &Leniency) -> Result<T>,
+) -> Result<Vec<T>> {
+    read_header(&mut data, 0x45)?;
+
+    let count = data.read_u16::<LittleEndian>().field("count")?;
+
+    // Many servers send a count which doesn't match the rules that
+    // follow, so they are read up to the end of the response instead
+    leniency.entries(&mut data, count as usize, read)
+}
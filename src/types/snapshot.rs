@@ -0,0 +1,60 @@
+use crate::errors::Error;
+#[cfg(any(feature = "sync", feature = "async"))]
+use crate::errors::Result;
+#[cfg(any(feature = "sync", feature = "async"))]
+use crate::types::Timed;
+use crate::types::{Info, Player, Rule};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize, Serializer};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// Info, players and rules of a server, queried together.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct ServerSnapshot {
+    pub info: Info,
+    /// `None` if the server didn't answer A2S_PLAYER with a valid response
+    pub players: Option<Vec<Player>>,
+    /// Why `players` is `None`. Serialized as its message, and not
+    /// deserialized.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "error_message", skip_deserializing)
+    )]
+    pub players_error: Option<Arc<Error>>,
+    /// `None` if the server didn't answer A2S_RULES with a valid response,
+    /// which is common as many servers disable it
+    pub rules: Option<Vec<Rule>>,
+    /// Why `rules` is `None`, as for `players_error`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "error_message", skip_deserializing)
+    )]
+    pub rules_error: Option<Arc<Error>>,
+    /// Round-trip time of the A2S_INFO query
+    pub rtt: Duration,
+    /// When the snapshot was queried
+    pub queried_at: SystemTime,
+}
+
+/// Splits the result of a query for part of a snapshot into the value and
+/// the error it failed with.
+#[cfg(any(feature = "sync", feature = "async"))]
+pub(crate) fn part<T>(result: Result<Timed<T>>) -> (Option<T>, Option<Arc<Error>>) {
+    match result {
+        Ok(timed) => (Some(timed.value), None),
+        Err(err) => (None, Some(Arc::new(err))),
+    }
+}
+
+#[cfg(feature = "serde")]
+fn error_message<S: Serializer>(
+    error: &Option<Arc<Error>>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    error
+        .as_ref()
+        .map(|err| err.to_string())
+        .serialize(serializer)
+}
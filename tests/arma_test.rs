@@ -0,0 +1,191 @@
+mod common;
+
+use crowbar_a2s::errors::Error;
+use crowbar_a2s::types::arma::{self, ArmaRules, Difficulty, Game, Mod};
+use crowbar_a2s::types::{RawRule, Rule};
+use std::io::Cursor;
+
+fn rule(name: &[u8], value: &[u8]) -> RawRule {
+    RawRule {
+        name: name.to_vec(),
+        value: value.to_vec(),
+    }
+}
+
+/// A2S_RULES response holding `rules`
+fn response(rules: &[RawRule]) -> Vec<u8> {
+    let mut bytes = vec![0xFF, 0xFF, 0xFF, 0xFF, 0x45];
+    bytes.extend((rules.len() as u16).to_le_bytes());
+    for rule in rules {
+        bytes.extend(rule.to_bytes());
+    }
+    bytes
+}
+
+/// Rules of an Arma 3 server running one mod, as sent
+fn arma_rules() -> Vec<RawRule> {
+    let mut data = Vec::new();
+    // Version 3, no overflow, the second DLC
+    data.extend(b"\x03\x01\x02\x02\x01\x02");
+    // Difficulty 2, AI level 1, third person, crosshair
+    data.extend(b"\x8A\x01\x01");
+    // DLC hash 0x20100001
+    data.extend(b"\x01\x01\x01\x02\x10\x20");
+    // One mod, hashed 0xFFFFFFFF, with a 4 byte workshop ID
+    data.extend(b"\x01\x01\x01\x03\x01\x03\x01\x03\x01\x03\x04");
+    data.extend(b"\x15\xCD\x5B\x07\x04CBA_");
+    // One signature
+    data.extend(b"\x01\x01\x03bis");
+
+    // Split within an escape sequence, and out of order
+    vec![
+        rule(b"hostname", b"Arma Stand-in"),
+        rule(&[2, 2], &data[12..]),
+        rule(&[1, 2], &data[..12]),
+    ]
+}
+
+#[test]
+fn test_decode_arma3() {
+    let details = ArmaRules::from_rules(&arma_rules(), Game::Arma3).unwrap();
+
+    assert_eq!(
+        details,
+        ArmaRules {
+            version: 3,
+            overflow: 0,
+            dlc: 0x0002,
+            dlc_hashes: vec![0x20100001],
+            difficulty: Some(Difficulty {
+                level: 2,
+                ai_level: 1,
+                advanced_flight_model: false,
+                third_person: true,
+                crosshair: true,
+            }),
+            mods: vec![Mod {
+                hash: 0xFFFFFFFF,
+                workshop_id: 123456789,
+                dlc: false,
+                name: "CBA_".to_string(),
+            }],
+            signatures: vec!["bis".to_string()],
+        }
+    );
+}
+
+#[test]
+fn test_round_trip() {
+    let details = ArmaRules {
+        version: 3,
+        overflow: 0,
+        dlc: 0x0101,
+        dlc_hashes: vec![0x00FF0100, 0x01010101],
+        difficulty: None,
+        mods: (0..20)
+            .map(|i| Mod {
+                hash: i,
+                workshop_id: 1_000_000_000 + i as u64,
+                dlc: i % 5 == 0,
+                name: format!("@mod_{}", i),
+            })
+            .collect(),
+        signatures: vec!["bi".to_string(), "cba_3".to_string()],
+    };
+
+    let rules = details.to_rules();
+    assert!(rules.len() > 1);
+    assert!(rules
+        .iter()
+        .all(|rule| !rule.value.contains(&0) && rule.value.len() <= 127));
+    assert_eq!(arma::reassemble(&rules).unwrap(), details.to_bytes());
+    assert_eq!(ArmaRules::from_rules(&rules, Game::DayZ).unwrap(), details);
+}
+
+#[test]
+fn test_stray_chunks() {
+    let details = ArmaRules::from_rules(&arma_rules(), Game::Arma3).unwrap();
+
+    // Rules named like chunks, alongside the actual ones, including an
+    // incomplete run as long as theirs
+    let mut rules = arma_rules();
+    rules.insert(0, rule(b"mp", b"1"));
+    rules.push(rule(&[1, 3], b"\x03"));
+    rules.push(rule(&[3, 3], b"\x04"));
+    assert_eq!(ArmaRules::from_rules(&rules, Game::Arma3).unwrap(), details);
+
+    // Of two complete runs, the longer one is kept
+    rules.push(rule(&[2, 3], b"\x05"));
+    assert_eq!(arma::reassemble(&rules).unwrap(), b"\x03\x05\x04");
+}
+
+#[test]
+fn test_invalid_chunks() {
+    let mut rules = arma_rules();
+    rules.remove(2);
+    assert!(matches!(
+        arma::reassemble(&rules),
+        Err(Error::MissingChunk { index: 1, count: 2 })
+    ));
+
+    // The same chunk sent twice
+    let mut rules = arma_rules();
+    rules.push(rule(&[2, 2], b"\x03"));
+    assert!(matches!(
+        arma::reassemble(&rules),
+        Err(Error::InvalidField { field: "chunk", .. })
+    ));
+
+    let rules = vec![rule(&[1, 1], &[0x03, 0x01, 0x04])];
+    assert!(matches!(
+        arma::reassemble(&rules),
+        Err(Error::InvalidField {
+            field: "escape",
+            ..
+        })
+    ));
+
+    // No chunks at all
+    let rules = vec![rule(b"hostname", b"Arma Stand-in")];
+    assert!(arma::reassemble(&rules).unwrap().is_empty());
+    assert!(matches!(
+        ArmaRules::from_rules(&rules, Game::Arma3),
+        Err(Error::InvalidField {
+            field: "version",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_raw_rules() {
+    let rules = vec![
+        rule(b"sv_hostname", b"\xC0\xF0\xEC\xE0"),
+        rule(&[1, 1], b"\x03"),
+    ];
+    let bytes = response(&rules);
+
+    assert_eq!(
+        RawRule::from_cursor(Cursor::new(bytes[4..].to_vec())).unwrap(),
+        rules
+    );
+
+    // Rules keep the bytes too
+    let text = Rule::from_cursor(Cursor::new(bytes[4..].to_vec())).unwrap();
+    assert_eq!(text[0].value.as_bytes(), b"\xC0\xF0\xEC\xE0");
+    assert_eq!(text[0], Rule::from(rules[0].clone()));
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_rules_raw_sync() {
+    let bytes = response(&arma_rules());
+    let addr = common::spawn_udp(move |_, _| vec![bytes.clone()]);
+
+    let client = crowbar_a2s::Builder::new().build_sync().unwrap();
+    let rules = client.rules_raw(addr).unwrap();
+    assert_eq!(rules, arma_rules());
+
+    let details = ArmaRules::from_rules(&rules, Game::Arma3).unwrap();
+    assert_eq!(details.mods[0].workshop_id, 123456789);
+}
@@ -0,0 +1,107 @@
+#![cfg(feature = "async")]
+
+mod common;
+
+use crowbar_a2s::errors::{Error, Stage};
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_batch_info() {
+    let mut addrs = common::spawn_stand_ins(50).await;
+    // bound but never answering
+    let silent = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    addrs.push(silent.local_addr().unwrap());
+    // duplicates are queried once
+    addrs.push(addrs[0]);
+
+    let client = crowbar_a2s::Builder::new()
+        .timeout(Duration::from_millis(300))
+        .build_async()
+        .unwrap();
+    let results: HashMap<_, _> = client
+        .batch_info(addrs.clone())
+        .await
+        .unwrap()
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect();
+
+    assert_eq!(results.len(), 51);
+    for addr in &addrs[..50] {
+        assert_eq!(results[addr].as_ref().unwrap().map, "de_dust2");
+    }
+    let err = results[&silent.local_addr().unwrap()].as_ref().unwrap_err();
+    assert!(matches!(
+        common::query_failure(err),
+        (Stage::Fragment(0), Error::ErrTimeout)
+    ));
+}
+
+#[tokio::test]
+async fn test_batch_stale_fragment() {
+    fn fragment(id: i32, number: u8, payload: &[u8]) -> Vec<u8> {
+        let mut datagram = vec![0xFE, 0xFF, 0xFF, 0xFF];
+        datagram.extend(id.to_le_bytes());
+        datagram.extend([2, number]);
+        datagram.extend(1400u16.to_le_bytes());
+        datagram.extend(payload);
+        datagram
+    }
+
+    // The first request goes unanswered, and a fragment sent for it only
+    // arrives along with the response to the retry
+    let mut received = 0;
+    let addr = common::spawn_udp(move |_, _| {
+        received += 1;
+        if received == 1 {
+            return Vec::new();
+        }
+        let bytes = common::info().to_bytes();
+        let (first, second) = bytes.split_at(bytes.len() / 2);
+        vec![
+            fragment(7, 0, first),
+            fragment(8, 0, first),
+            fragment(8, 1, second),
+        ]
+    });
+
+    let client = crowbar_a2s::Builder::new()
+        .timeout(Duration::from_millis(100))
+        .retry(crowbar_a2s::RetryPolicy {
+            attempts: 2,
+            base_delay: Duration::from_millis(10),
+            ..Default::default()
+        })
+        .build_async()
+        .unwrap();
+    let results: Vec<_> = client.batch_info(vec![addr]).await.unwrap().collect().await;
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].1.as_ref().unwrap().map, "de_dust2");
+}
+
+#[tokio::test]
+async fn test_batch_players_and_rules() {
+    let addrs = common::spawn_stand_ins(10).await;
+    let client = crowbar_a2s::Builder::new().build_async().unwrap();
+
+    let players: Vec<_> = client
+        .batch_players(addrs.clone())
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    let rules: Vec<_> = client.batch_rules(addrs).await.unwrap().collect().await;
+
+    assert_eq!(players.len(), 10);
+    assert!(players
+        .iter()
+        .all(|(_, players)| players.as_ref().unwrap().len() == 2));
+    assert_eq!(rules.len(), 10);
+    assert!(rules
+        .iter()
+        .all(|(_, rules)| rules.as_ref().unwrap()[1].name == "sv_gravity"));
+}
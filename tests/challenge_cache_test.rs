@@ -0,0 +1,93 @@
+#![cfg(any(feature = "sync", feature = "async"))]
+
+mod common;
+
+use common::StandIn;
+use crowbar_a2s::server::Responder;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Spawns a stand-in server counting the requests it receives. It restarts,
+/// issuing new challenges, after `restart_after` requests.
+fn spawn_counting(restart_after: usize) -> (SocketAddr, Arc<AtomicUsize>) {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+    let mut responder = Responder::new(StandIn);
+    responder.info_challenge(true);
+
+    let addr = common::spawn_udp(move |request, client| {
+        let received = counter.fetch_add(1, Ordering::SeqCst) + 1;
+        let response = responder.respond(request, client).unwrap();
+        if received == restart_after {
+            responder = Responder::new(StandIn);
+            responder.info_challenge(true);
+        }
+        response
+    });
+
+    (addr, requests)
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_challenge_cache_sync() {
+    let (addr, requests) = spawn_counting(usize::MAX);
+    let client = crowbar_a2s::Builder::new()
+        .challenge_cache(Duration::from_secs(30))
+        .build_sync()
+        .unwrap();
+
+    client.players(addr).unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+    // The challenge is shared by every kind of request
+    client.rules(addr).unwrap();
+    client.info(addr).unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), 4);
+
+    // Without a cache, every query is challenged
+    let client = crowbar_a2s::Builder::new().build_sync().unwrap();
+    client.rules(addr).unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), 6);
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_challenge_cache_async() {
+    use futures::StreamExt;
+
+    let (addr, requests) = spawn_counting(usize::MAX);
+    let client = crowbar_a2s::Builder::new()
+        .challenge_cache(Duration::from_secs(30))
+        .build_async()
+        .unwrap();
+
+    client.info(addr).await.unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+    let players: Vec<_> = client.batch_players([addr]).await.unwrap().collect().await;
+    assert!(players[0].1.is_ok());
+    client.rules(addr).await.unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), 4);
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_challenge_cache_refresh() {
+    let (addr, requests) = spawn_counting(2);
+    let client = crowbar_a2s::Builder::new()
+        .challenge_cache(Duration::from_secs(30))
+        .build_async()
+        .unwrap();
+    client.players(addr).await.unwrap();
+
+    // The server restarted, the cached challenge is stale
+    let rules = client.rules(addr).await.unwrap();
+    assert_eq!(rules.len(), 2);
+    assert_eq!(requests.load(Ordering::SeqCst), 4);
+
+    client.info(addr).await.unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), 5);
+}
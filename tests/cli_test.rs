@@ -0,0 +1,134 @@
+#![cfg(feature = "cli")]
+
+mod common;
+
+use std::net::UdpSocket;
+use std::process::{Command, Output};
+
+fn a2s(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_a2s"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_cli_tables() {
+    let addr = common::spawn_stand_in().to_string();
+
+    let output = a2s(&["info", &addr]);
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Map       de_dust2"), "{}", stdout);
+    assert!(stdout.contains("Players   2/24 (0 bots)"), "{}", stdout);
+
+    let output = a2s(&["players", &addr]);
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("alice  12     0:10:00"), "{}", stdout);
+
+    let output = a2s(&["snapshot", &addr]);
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("sv_gravity"), "{}", stdout);
+    assert!(stdout.contains("RTT"), "{}", stdout);
+}
+
+#[test]
+fn test_cli_json() {
+    let addrs = [
+        common::spawn_stand_in().to_string(),
+        common::spawn_stand_in().to_string(),
+    ];
+
+    let output = a2s(&["rules", "--json", &addrs[0], &addrs[1]]);
+    assert_eq!(output.status.code(), Some(0));
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    for (result, addr) in json.as_array().unwrap().iter().zip(&addrs) {
+        assert_eq!(result["address"], addr.as_str());
+        assert_eq!(result["rules"][1]["name"], "sv_gravity");
+        assert_eq!(result["rules"][1]["value"], "800");
+    }
+}
+
+#[test]
+fn test_cli_many_servers() {
+    // More servers than are queried at once
+    let addrs: Vec<String> = (0..40)
+        .map(|_| common::spawn_stand_in().to_string())
+        .collect();
+
+    let mut args = vec!["info", "--json"];
+    args.extend(addrs.iter().map(String::as_str));
+    let output = a2s(&args);
+    assert_eq!(output.status.code(), Some(0));
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let results = json.as_array().unwrap();
+    assert_eq!(results.len(), addrs.len());
+    for (result, addr) in results.iter().zip(&addrs) {
+        assert_eq!(result["address"], addr.as_str());
+        assert_eq!(result["info"]["map"], "de_dust2");
+    }
+}
+
+#[test]
+fn test_cli_exit_codes() {
+    // Bound, so datagrams are silently dropped rather than refused
+    let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let silent = silent.local_addr().unwrap().to_string();
+    let addr = common::spawn_stand_in().to_string();
+
+    let output = a2s(&["info", "--timeout", "0.2", "--json", &addr, &silent]);
+    assert_eq!(output.status.code(), Some(3));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json[0]["info"]["map"], "de_dust2");
+    assert_eq!(json[1]["error"]["kind"], "timeout");
+
+    assert_eq!(a2s(&["info"]).status.code(), Some(2));
+    assert_eq!(a2s(&["status", &addr]).status.code(), Some(2));
+    assert_eq!(
+        a2s(&["info", "--timeout", "soon", &addr]).status.code(),
+        Some(2)
+    );
+    assert_eq!(a2s(&["--help"]).status.code(), Some(0));
+}
+
+#[test]
+fn test_cli_goldsrc_info() {
+    use crowbar_a2s::types::info::{ServerOS, ServerType};
+    use crowbar_a2s::types::GoldSrcInfo;
+
+    // Answers with the obsolete GoldSrc response
+    let info = GoldSrcInfo {
+        address: "127.0.0.1:27015".into(),
+        name: "Half-Life Server".into(),
+        map: "crossfire".into(),
+        folder: "valve".into(),
+        game: "Half-Life".into(),
+        players: 3,
+        max_players: 16,
+        protocol: 47,
+        server_type: ServerType::Dedicated,
+        server_os: ServerOS::Linux,
+        visibility: false,
+        mod_info: None,
+        vac: true,
+        bots: 0,
+    };
+    let bytes = info.to_bytes();
+    let addr = common::spawn_udp(move |_, _| vec![bytes.clone()]).to_string();
+
+    let output = a2s(&["info", "--goldsrc", &addr]);
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Map       crossfire"), "{}", stdout);
+    assert!(stdout.contains("Address   127.0.0.1:27015"), "{}", stdout);
+
+    let output = a2s(&["info", "--goldsrc", "--json", &addr]);
+    assert_eq!(output.status.code(), Some(0));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json[0]["info"]["map"], "crossfire");
+    assert_eq!(json[0]["info"]["address"], "127.0.0.1:27015");
+}
@@ -0,0 +1,151 @@
+#![allow(dead_code)]
+
+use crowbar_a2s::errors::{Error, Stage};
+use crowbar_a2s::server::{Responder, ServerState};
+use crowbar_a2s::types::info::{ExtendedServerInfo, ServerOS, ServerType};
+use crowbar_a2s::types::{Info, Player, Rule};
+use std::net::{SocketAddr, UdpSocket};
+use std::time::Duration;
+
+pub fn info() -> Info {
+    Info {
+        protocol: 17,
+        name: "Stand-in Server".into(),
+        map: "de_dust2".into(),
+        folder: "csgo".into(),
+        game: "Counter-Strike: Global Offensive".into(),
+        app_id: 730,
+        players: 2,
+        max_players: 24,
+        bots: 0,
+        server_type: ServerType::Dedicated,
+        server_os: ServerOS::Linux,
+        visibility: false,
+        vac: true,
+        the_ship: None,
+        version: "1.38.7.9".into(),
+        edf: 0xB1,
+        extended_server_info: ExtendedServerInfo {
+            port: Some(27015),
+            steam_id: Some(90071996842377216),
+            keywords: Some("secure,valve_ds".into()),
+            game_id: Some(730),
+        },
+        source_tv: None,
+    }
+}
+
+pub fn players() -> Vec<Player> {
+    vec![
+        Player {
+            index: 0,
+            name: "alice".into(),
+            score: 12,
+            duration: 600.5,
+            the_ship: None,
+        },
+        Player {
+            index: 0,
+            name: "bob".into(),
+            score: 3,
+            duration: 42.0,
+            the_ship: None,
+        },
+    ]
+}
+
+pub fn rules() -> Vec<Rule> {
+    vec![
+        Rule {
+            name: "mp_friendlyfire".into(),
+            value: "0".into(),
+        },
+        Rule {
+            name: "sv_gravity".into(),
+            value: "800".into(),
+        },
+    ]
+}
+
+/// Server state answering the fixtures above
+pub struct StandIn;
+
+impl ServerState for StandIn {
+    fn info(&self) -> Info {
+        info()
+    }
+
+    fn players(&self) -> Vec<Player> {
+        players()
+    }
+
+    fn rules(&self) -> Vec<Rule> {
+        rules()
+    }
+}
+
+/// Stage and source of `err`, as wrapped by the query which failed.
+pub fn query_failure(err: &Error) -> (Stage, &Error) {
+    match err {
+        Error::Query(context) => (context.stage, &context.source),
+        err => panic!("expected query context, got {:?}", err),
+    }
+}
+
+/// Spawns a thread answering every datagram sent to the returned address
+/// with the datagrams `respond` makes of it.
+pub fn spawn_udp<F>(respond: F) -> SocketAddr
+where
+    F: FnMut(&[u8], SocketAddr) -> Vec<Vec<u8>> + Send + 'static,
+{
+    spawn_paced(Duration::ZERO, respond)
+}
+
+/// Like [`spawn_udp`], sleeping `gap` before sending each datagram.
+pub fn spawn_paced<F>(gap: Duration, mut respond: F) -> SocketAddr
+where
+    F: FnMut(&[u8], SocketAddr) -> Vec<Vec<u8>> + Send + 'static,
+{
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = socket.local_addr().unwrap();
+
+    std::thread::spawn(move || {
+        let mut data = [0; 1400];
+
+        loop {
+            let (read, client) = socket.recv_from(&mut data).unwrap();
+            for datagram in respond(&data[..read], client) {
+                if !gap.is_zero() {
+                    std::thread::sleep(gap);
+                }
+                socket.send_to(&datagram, client).unwrap();
+            }
+        }
+    });
+
+    addr
+}
+
+/// Spawns a thread answering with `responder` at the returned address.
+pub fn spawn_responder<S: ServerState + Send + 'static>(responder: Responder<S>) -> SocketAddr {
+    spawn_udp(move |request, client| responder.respond(request, client).unwrap())
+}
+
+/// Spawns a thread answering as [`StandIn`] at the returned address.
+pub fn spawn_stand_in() -> SocketAddr {
+    spawn_responder(Responder::new(StandIn))
+}
+
+/// Spawns `count` async servers answering as [`StandIn`].
+#[cfg(feature = "async")]
+pub async fn spawn_stand_ins(count: usize) -> Vec<SocketAddr> {
+    use crowbar_a2s::server::A2SServerAsync;
+
+    let mut addrs = Vec::new();
+    for _ in 0..count {
+        let server = A2SServerAsync::bind("127.0.0.1:0", StandIn).await.unwrap();
+        addrs.push(server.local_addr().unwrap());
+        tokio::spawn(async move { server.run().await });
+    }
+    addrs
+}
@@ -0,0 +1,191 @@
+mod common;
+
+use crowbar_a2s::types::{Player, Rule, Warning};
+use std::io::Cursor;
+
+/// Payload of a response, without the single packet header
+fn payload(datagram: &[u8]) -> Cursor<Vec<u8>> {
+    Cursor::new(datagram[4..].to_vec())
+}
+
+/// Rules response with the rule count replaced by `count`
+fn rules_counting(count: u16) -> Vec<u8> {
+    let mut bytes = Rule::vec_to_bytes(common::rules());
+    bytes[5..7].copy_from_slice(&count.to_le_bytes());
+    bytes
+}
+
+fn names(players: &[Player]) -> Vec<&str> {
+    players
+        .iter()
+        .map(|player| player.name.to_str().unwrap())
+        .collect()
+}
+
+fn many_players(count: usize) -> Vec<Player> {
+    (0..count)
+        .map(|i| Player {
+            index: 0,
+            name: format!("player{}", i).into(),
+            score: i as i32,
+            duration: 1.0,
+            the_ship: None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_rule_count_mismatch() {
+    // More rules announced than sent, as with host_rules_show 0
+    let bytes = rules_counting(5);
+    assert_eq!(Rule::from_cursor(payload(&bytes)).unwrap(), common::rules());
+
+    let counted = Rule::from_cursor_counted(payload(&bytes)).unwrap();
+    assert_eq!(counted.entries, common::rules());
+    assert_eq!((counted.declared, counted.actual()), (5, 2));
+
+    let parsed = Rule::from_cursor_lenient(payload(&bytes)).unwrap();
+    assert_eq!(parsed.value, common::rules());
+    assert_eq!(parsed.declared, Some(5));
+    assert!(!parsed.truncated);
+    assert_eq!(
+        parsed.warnings,
+        vec![Warning::CountMismatch {
+            declared: 5,
+            actual: 2
+        }]
+    );
+
+    // Fewer rules announced than sent
+    let bytes = rules_counting(0);
+    assert_eq!(Rule::from_cursor(payload(&bytes)).unwrap(), common::rules());
+
+    let parsed = Rule::from_cursor_lenient(payload(&bytes)).unwrap();
+    assert_eq!(parsed.declared, Some(0));
+    assert_eq!(
+        parsed.warnings,
+        vec![Warning::CountMismatch {
+            declared: 0,
+            actual: 2
+        }]
+    );
+
+    // Matching counts aren't worth a warning
+    let bytes = Rule::vec_to_bytes(common::rules());
+    let parsed = Rule::from_cursor_lenient(payload(&bytes)).unwrap();
+    assert_eq!(parsed.declared, Some(2));
+    assert!(parsed.warnings.is_empty());
+}
+
+#[test]
+fn test_player_count_mismatch() {
+    let mut bytes = Player::vec_to_bytes(common::players());
+    bytes[5] = 7;
+
+    let players = Player::from_cursor(payload(&bytes), 0).unwrap();
+    assert_eq!(names(&players), ["alice", "bob"]);
+
+    let parsed = Player::from_cursor_lenient(payload(&bytes), 0).unwrap();
+    assert_eq!(names(&parsed.value), ["alice", "bob"]);
+    assert_eq!(parsed.declared, Some(7));
+    assert_eq!(
+        parsed.warnings,
+        vec![Warning::CountMismatch {
+            declared: 7,
+            actual: 2
+        }]
+    );
+}
+
+#[test]
+fn test_trailing_padding() {
+    // Padding past the announced rules is no rule
+    let mut bytes = Rule::vec_to_bytes(common::rules());
+    bytes.push(0);
+    assert_eq!(Rule::from_cursor(payload(&bytes)).unwrap(), common::rules());
+
+    let counted = Rule::from_cursor_counted(payload(&bytes)).unwrap();
+    assert_eq!((counted.declared, counted.actual()), (2, 2));
+
+    // Even when long enough to read as empty rules
+    bytes.extend([0, 0]);
+    assert_eq!(Rule::from_cursor(payload(&bytes)).unwrap(), common::rules());
+    let counted = Rule::from_cursor_counted(payload(&bytes)).unwrap();
+    assert_eq!((counted.declared, counted.actual()), (2, 2));
+    let parsed = Rule::from_cursor_lenient(payload(&bytes)).unwrap();
+    assert!(!parsed.truncated);
+    assert!(parsed.warnings.is_empty());
+
+    let mut bytes = Player::vec_to_bytes(common::players());
+    bytes.extend([0, 0]);
+    let players = Player::from_cursor(payload(&bytes), 0).unwrap();
+    assert_eq!(names(&players), ["alice", "bob"]);
+
+    // Short of the announced players, the response is still cut short
+    bytes[5] = 3;
+    assert!(Player::from_cursor(payload(&bytes), 0).is_err());
+
+    // Padding long enough to read as a player
+    bytes[5] = 2;
+    bytes.extend([0; 16]);
+    let counted = Player::from_cursor_counted(payload(&bytes), 0).unwrap();
+    assert_eq!((counted.declared, counted.actual()), (2, 2));
+}
+
+#[test]
+fn test_over_255_players() {
+    let players = many_players(300);
+    let bytes = Player::vec_to_bytes(players.clone());
+
+    // The count saturates, rather than wrapping around
+    assert_eq!(bytes[5], 255);
+    let parsed = Player::from_cursor(payload(&bytes), 0).unwrap();
+    assert_eq!(names(&parsed), names(&players));
+
+    let parsed = Player::from_cursor_lenient(payload(&bytes), 0).unwrap();
+    assert_eq!(parsed.value.len(), 300);
+    assert_eq!(parsed.declared, Some(255));
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_over_255_players_sync() {
+    use crowbar_a2s::server::{Responder, ServerState};
+    use crowbar_a2s::types::{Info, Rule};
+
+    struct Crowded;
+
+    impl ServerState for Crowded {
+        fn info(&self) -> Info {
+            common::info()
+        }
+
+        fn players(&self) -> Vec<Player> {
+            many_players(300)
+        }
+
+        fn rules(&self) -> Vec<Rule> {
+            common::rules()
+        }
+    }
+
+    let addr = common::spawn_responder(Responder::new(Crowded));
+
+    let client = crowbar_a2s::Builder::new().build_sync().unwrap();
+    let players = client.players(addr).unwrap();
+    assert_eq!(names(&players), names(&many_players(300)));
+
+    let counted = client.players_counted(addr).unwrap();
+    assert_eq!((counted.declared, counted.actual()), (255, 300));
+
+    let parsed = client.players_lenient(addr).unwrap();
+    assert_eq!(parsed.value.len(), 300);
+    assert_eq!(parsed.declared, Some(255));
+    assert_eq!(
+        parsed.warnings,
+        vec![Warning::CountMismatch {
+            declared: 255,
+            actual: 300
+        }]
+    );
+}
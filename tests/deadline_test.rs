@@ -0,0 +1,116 @@
+#![cfg(any(feature = "sync", feature = "async"))]
+
+mod common;
+
+use common::StandIn;
+use crowbar_a2s::errors::{Error, Stage};
+use crowbar_a2s::server::Responder;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// Spawns a stand-in server splitting its responses into small fragments,
+/// sent `gap` apart. Only the first `fragments` fragments of a response are
+/// sent.
+fn spawn_trickle(gap: Duration, fragments: usize) -> SocketAddr {
+    let mut responder = Responder::new(StandIn);
+    responder.max_size(20);
+
+    common::spawn_paced(gap, move |request, client| {
+        let mut response = responder.respond(request, client).unwrap();
+        response.truncate(fragments);
+        response
+    })
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_deadline_sync() {
+    let client = crowbar_a2s::Builder::new()
+        .timeout(Duration::from_millis(500))
+        .build_sync()
+        .unwrap();
+
+    let rules = client.rules(spawn_trickle(Duration::ZERO, usize::MAX));
+    assert_eq!(rules.unwrap().len(), 2);
+
+    // Every fragment arrives within the timeout, the whole response doesn't
+    let rules = client.rules(spawn_trickle(Duration::from_millis(100), usize::MAX));
+    assert!(matches!(
+        common::query_failure(&rules.unwrap_err()),
+        (Stage::Fragment(_), Error::Io(_))
+    ));
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_fragment_timeout_sync() {
+    let client = crowbar_a2s::Builder::new()
+        .timeout(Duration::from_secs(5))
+        .fragment_timeout(Duration::from_millis(100))
+        .build_sync()
+        .unwrap();
+
+    let start = std::time::Instant::now();
+    let rules = client.rules(spawn_trickle(Duration::ZERO, 2));
+    assert!(matches!(
+        common::query_failure(&rules.unwrap_err()),
+        (Stage::Fragment(2), Error::Io(_))
+    ));
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_deadline_async() {
+    let client = crowbar_a2s::Builder::new()
+        .timeout(Duration::from_millis(500))
+        .build_async()
+        .unwrap();
+
+    let rules = client
+        .rules(spawn_trickle(Duration::ZERO, usize::MAX))
+        .await;
+    assert_eq!(rules.unwrap().len(), 2);
+
+    let rules = client
+        .rules(spawn_trickle(Duration::from_millis(100), usize::MAX))
+        .await;
+    assert!(matches!(
+        common::query_failure(&rules.unwrap_err()),
+        (Stage::Fragment(_), Error::ErrTimeout)
+    ));
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_deadline_batch() {
+    use futures::StreamExt;
+
+    let fast = spawn_trickle(Duration::ZERO, usize::MAX);
+    let slow = spawn_trickle(Duration::from_millis(60), usize::MAX);
+    let stalled = spawn_trickle(Duration::ZERO, 2);
+
+    let client = crowbar_a2s::Builder::new()
+        .timeout(Duration::from_millis(300))
+        .fragment_timeout(Duration::from_millis(100))
+        .build_async()
+        .unwrap();
+    let results: Vec<_> = client
+        .batch_rules([fast, slow, stalled])
+        .await
+        .unwrap()
+        .collect()
+        .await;
+
+    assert_eq!(results.len(), 3);
+    for (addr, rules) in results {
+        if addr == fast {
+            assert_eq!(rules.unwrap().len(), 2);
+        } else {
+            assert!(matches!(
+                common::query_failure(&rules.unwrap_err()),
+                (Stage::Fragment(_), Error::ErrTimeout)
+            ));
+        }
+    }
+}
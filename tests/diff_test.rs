@@ -0,0 +1,126 @@
+mod common;
+
+use crowbar_a2s::types::diff::{self, FieldChange, InfoChange, PlayerChange, RuleChange};
+use crowbar_a2s::types::info::ServerOS;
+use crowbar_a2s::types::{Player, Rule};
+
+fn player(name: &str, duration: f32) -> Player {
+    Player {
+        index: 0,
+        name: name.into(),
+        score: 0,
+        duration,
+        the_ship: None,
+    }
+}
+
+fn names(changes: &[PlayerChange]) -> Vec<String> {
+    changes
+        .iter()
+        .map(|change| match change {
+            PlayerChange::Joined(player) => format!("+{} {}", player.name, player.duration),
+            PlayerChange::Left(player) => format!("-{} {}", player.name, player.duration),
+        })
+        .collect()
+}
+
+#[test]
+fn test_diff_info() {
+    let old = common::info();
+    assert!(diff::info(&old, &old).is_empty());
+
+    let mut new = common::info();
+    new.map = "de_nuke".into();
+    new.players = 5;
+    new.server_os = ServerOS::Windows;
+    new.extended_server_info.keywords = None;
+
+    assert_eq!(
+        diff::info(&old, &new),
+        vec![
+            InfoChange::Map(FieldChange {
+                from: "de_dust2".into(),
+                to: "de_nuke".into(),
+            }),
+            InfoChange::Players(FieldChange { from: 2, to: 5 }),
+            InfoChange::ServerOS(FieldChange {
+                from: ServerOS::Linux,
+                to: ServerOS::Windows,
+            }),
+            InfoChange::Keywords(FieldChange {
+                from: Some("secure,valve_ds".into()),
+                to: None,
+            }),
+        ]
+    );
+}
+
+#[test]
+fn test_diff_players() {
+    let old = vec![player("alice", 600.0), player("bob", 42.0)];
+    let new = vec![
+        player("bob", 71.5),
+        player("alice", 20.0),
+        player("carol", 3.0),
+    ];
+
+    // alice reconnected
+    assert_eq!(
+        names(&diff::players(&old, &new)),
+        ["-alice 600", "+alice 20", "+carol 3"]
+    );
+
+    // Connection times are rounded by some servers
+    let new = vec![player("alice", 599.5), player("bob", 42.0)];
+    assert!(diff::players(&old, &new).is_empty());
+}
+
+#[test]
+fn test_diff_players_duplicate_names() {
+    let old = vec![
+        player("Player", 10.0),
+        player("Player", 300.0),
+        player("Player", 50.0),
+    ];
+
+    // The player connected for 50 seconds left, another one joined
+    let new = vec![
+        player("Player", 330.0),
+        player("Player", 40.0),
+        player("Player", 2.0),
+    ];
+    assert_eq!(
+        names(&diff::players(&old, &new)),
+        ["-Player 50", "+Player 2"]
+    );
+
+    // Everyone left, and as many players of the same name joined
+    let new = vec![player("Player", 1.0), player("Player", 1.0)];
+    assert_eq!(diff::players(&old, &new).len(), 5);
+}
+
+#[test]
+fn test_diff_rules() {
+    let rule = |name: &str, value: &str| Rule {
+        name: name.into(),
+        value: value.into(),
+    };
+    let old = vec![rule("mp_timelimit", "30"), rule("sv_cheats", "0")];
+    let new = vec![rule("sv_cheats", "1"), rule("sv_tags", "hardcore")];
+
+    assert_eq!(
+        diff::rules(&old, &new),
+        vec![
+            RuleChange::Removed(rule("mp_timelimit", "30")),
+            RuleChange::Modified {
+                name: "sv_cheats".into(),
+                value: FieldChange {
+                    from: "0".into(),
+                    to: "1".into(),
+                },
+            },
+            RuleChange::Added(rule("sv_tags", "hardcore")),
+        ]
+    );
+    assert!(diff::rules(&new, &new).is_empty());
+}
@@ -0,0 +1,138 @@
+mod common;
+
+use crowbar_a2s::packet::Encoder;
+use crowbar_a2s::{Engine, Reassembler};
+
+fn payload(len: usize) -> Vec<u8> {
+    let mut payload = vec![0xFF, 0xFF, 0xFF, 0xFF, 0x45];
+    payload.extend((0..len).map(|i| (i % 251) as u8));
+    payload
+}
+
+fn reassemble(reassembler: &mut Reassembler, datagrams: Vec<Vec<u8>>) -> Vec<u8> {
+    let count = datagrams.len();
+    for (i, datagram) in datagrams.into_iter().enumerate().rev() {
+        let result = reassembler.push(&datagram).unwrap();
+        if i == 0 {
+            return result.unwrap();
+        }
+        assert!(result.is_none(), "completed after {} of {count}", count - i);
+    }
+    unreachable!()
+}
+
+#[test]
+fn test_encode_single() {
+    let payload = payload(100);
+
+    let datagrams = Encoder::new(1400).encode(1, &payload).unwrap();
+
+    assert_eq!(datagrams, vec![payload]);
+}
+
+#[test]
+fn test_encode_split() {
+    let payload = payload(5000);
+
+    let datagrams = Encoder::new(1248).encode(9, &payload).unwrap();
+
+    assert_eq!(datagrams.len(), 5);
+    assert!(datagrams.iter().all(|d| d.len() <= 1248));
+    assert_eq!(&datagrams[0][..4], &(-2i32).to_le_bytes());
+    assert_eq!(&datagrams[0][4..8], &9i32.to_le_bytes());
+    assert_eq!(datagrams[3][8], 5);
+    assert_eq!(datagrams[3][9], 3);
+    assert_eq!(&datagrams[3][10..12], &1248u16.to_le_bytes());
+    assert_eq!(
+        reassemble(&mut Reassembler::new(1400), datagrams),
+        &payload[4..]
+    );
+}
+
+#[test]
+fn test_encode_bz2() {
+    let payload = payload(20000);
+
+    let datagrams = Encoder::new(1400)
+        .compress(true)
+        .encode(2, &payload)
+        .unwrap();
+
+    assert!(datagrams[0][7] & 0x80 != 0);
+    assert_eq!(&datagrams[0][12..16], &(payload.len() as u32).to_le_bytes());
+    assert_eq!(
+        reassemble(&mut Reassembler::new(1400), datagrams),
+        &payload[4..]
+    );
+}
+
+#[test]
+fn test_encode_goldsrc() {
+    let payload = payload(3000);
+
+    let datagrams = Encoder::new(1400)
+        .engine(Engine::GoldSrc)
+        .encode(4, &payload)
+        .unwrap();
+
+    assert_eq!(datagrams.len(), 3);
+    assert_eq!(datagrams[2][8], 0x23);
+    assert_eq!(
+        reassemble(
+            &mut Reassembler::with_engine(1400, Engine::GoldSrc),
+            datagrams
+        ),
+        &payload[4..]
+    );
+}
+
+#[test]
+fn test_encode_too_large() {
+    assert!(Encoder::new(1400).encode(1, &payload(100000)).is_err());
+    assert!(Encoder::new(10).encode(1, &payload(100)).is_err());
+}
+
+#[test]
+#[should_panic(expected = "above 65535")]
+fn test_max_size_too_large() {
+    Encoder::new(65536);
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_server_split_rules() {
+    use crowbar_a2s::server::{A2SServer, ServerState};
+    use crowbar_a2s::types::{Info, Player, Rule};
+
+    struct ManyRules;
+
+    impl ServerState for ManyRules {
+        fn info(&self) -> Info {
+            common::info()
+        }
+
+        fn players(&self) -> Vec<Player> {
+            Vec::new()
+        }
+
+        fn rules(&self) -> Vec<Rule> {
+            (0..500)
+                .map(|i| Rule {
+                    name: format!("rule_{i}").into(),
+                    value: format!("value_{i}").into(),
+                })
+                .collect()
+        }
+    }
+
+    let mut server = A2SServer::bind("127.0.0.1:0", ManyRules).unwrap();
+    server.responder().encoder().compress(true);
+    let addr = server.local_addr().unwrap();
+    std::thread::spawn(move || server.run());
+
+    let client = crowbar_a2s::Builder::new().build_sync().unwrap();
+    let rules = client.rules(addr).unwrap();
+
+    assert_eq!(rules.len(), 500);
+    assert_eq!(rules[499].name, "rule_499");
+}
@@ -0,0 +1,187 @@
+mod common;
+
+use crowbar_a2s::errors::Error;
+#[cfg(any(feature = "sync", feature = "async"))]
+use crowbar_a2s::errors::{QueryError, Stage};
+#[cfg(any(feature = "sync", feature = "async"))]
+use crowbar_a2s::query::QueryKind;
+use crowbar_a2s::types::Info;
+use std::io::Cursor;
+#[cfg(any(feature = "sync", feature = "async"))]
+use std::net::SocketAddr;
+
+/// Spawns a server answering every request with `datagrams`.
+#[cfg(any(feature = "sync", feature = "async"))]
+fn spawn_scripted(datagrams: Vec<Vec<u8>>) -> SocketAddr {
+    common::spawn_udp(move |_, _| datagrams.clone())
+}
+
+/// A2S_INFO response cut short after the game name
+fn truncated_info() -> Vec<u8> {
+    let mut datagram = vec![0xFF, 0xFF, 0xFF, 0xFF, 0x49, 0x11];
+    datagram.extend(b"name\0map\0folder\0game\0");
+    datagram
+}
+
+/// Fragment `number` of a split response with `id`
+#[cfg(any(feature = "sync", feature = "async"))]
+fn fragment(id: i32, total: u8, number: u8, payload: &[u8]) -> Vec<u8> {
+    let mut datagram = vec![0xFE, 0xFF, 0xFF, 0xFF];
+    datagram.extend(id.to_le_bytes());
+    datagram.extend([total, number]);
+    datagram.extend(1400u16.to_le_bytes());
+    datagram.extend(payload);
+    datagram
+}
+
+#[cfg(any(feature = "sync", feature = "async"))]
+fn query_error(err: Error) -> QueryError {
+    match err {
+        Error::Query(context) => *context,
+        err => panic!("expected query context, got {:?}", err),
+    }
+}
+
+#[test]
+fn test_field_errors() {
+    let result = Info::from_cursor(Cursor::new(truncated_info()[4..].to_vec()));
+    match result {
+        Err(Error::InvalidField { field, source }) => {
+            assert_eq!(field, "app_id");
+            assert!(matches!(*source, Error::Io(_)));
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+
+    let result = Info::from_cursor(Cursor::new(vec![0x44, 0x00]));
+    assert!(matches!(
+        result,
+        Err(Error::UnexpectedHeader {
+            expected: 0x49,
+            found: 0x44
+        })
+    ));
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_parse_context_sync() {
+    let addr = spawn_scripted(vec![truncated_info()]);
+    let client = crowbar_a2s::Builder::new()
+        .capture_datagrams(true)
+        .build_sync()
+        .unwrap();
+
+    let err = client.info(addr).unwrap_err();
+    assert!(err.to_string().starts_with(&format!(
+        "Info query to {} failed at parse: Invalid app_id",
+        addr
+    )));
+
+    let context = query_error(err);
+    assert_eq!(context.server, addr);
+    assert_eq!(context.kind, QueryKind::Info);
+    assert_eq!(context.stage, Stage::Parse);
+    assert_eq!(context.datagrams, vec![truncated_info()]);
+    assert!(matches!(
+        context.source,
+        Error::InvalidField {
+            field: "app_id",
+            ..
+        }
+    ));
+
+    // Datagrams are only kept on request
+    let client = crowbar_a2s::Builder::new().build_sync().unwrap();
+    let addr = spawn_scripted(vec![vec![0xFF, 0xFF, 0xFF, 0xFF, 0x49, 0x11]]);
+    let context = query_error(client.players(addr).unwrap_err());
+    assert_eq!(context.kind, QueryKind::Players);
+    assert_eq!(context.stage, Stage::Parse);
+    assert!(context.datagrams.is_empty());
+    assert!(matches!(
+        context.source,
+        Error::UnexpectedHeader {
+            expected: 0x44,
+            found: 0x49
+        }
+    ));
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_reassembly_context_sync() {
+    let client = crowbar_a2s::Builder::new()
+        .capture_datagrams(true)
+        .build_sync()
+        .unwrap();
+
+    let fragments = vec![fragment(1, 2, 0, b"first"), fragment(2, 2, 1, b"second")];
+    let context = query_error(client.rules(spawn_scripted(fragments.clone())).unwrap_err());
+    assert_eq!(context.stage, Stage::Fragment(1));
+    assert_eq!(context.datagrams, fragments);
+    assert!(matches!(context.source, Error::MismatchID));
+
+    // Compressed, claiming 16 MiB once decompressed
+    let mut payload = (16u32 << 20).to_le_bytes().to_vec();
+    payload.extend([0; 8]);
+    let compressed = fragment(0x80000001u32 as i32, 1, 0, &payload);
+    let context = query_error(client.rules(spawn_scripted(vec![compressed])).unwrap_err());
+    assert_eq!(context.stage, Stage::Decompression);
+    assert!(matches!(context.source, Error::InvalidBz2Size));
+
+    // Servers issuing challenges forever
+    let challenge = vec![0xFF, 0xFF, 0xFF, 0xFF, 0x41, 0x01, 0x02, 0x03, 0x04];
+    let context = query_error(
+        client
+            .players(spawn_scripted(vec![challenge.clone()]))
+            .unwrap_err(),
+    );
+    assert_eq!(context.stage, Stage::Challenge);
+    assert_eq!(context.datagrams, vec![challenge]);
+    assert!(matches!(context.source, Error::InvalidResponse));
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_parse_context_async() {
+    let addr = spawn_scripted(vec![truncated_info()]);
+    let client = crowbar_a2s::Builder::new()
+        .capture_datagrams(true)
+        .build_async()
+        .unwrap();
+
+    let context = query_error(client.info(addr).await.unwrap_err());
+    assert_eq!(context.server, addr);
+    assert_eq!(context.stage, Stage::Parse);
+    assert_eq!(context.datagrams, vec![truncated_info()]);
+
+    let context = query_error(client.snapshot(addr).await.unwrap_err());
+    assert_eq!(context.kind, QueryKind::Info);
+    assert_eq!(context.stage, Stage::Parse);
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_timeout_context_async() {
+    // The first of two fragments, and nothing after it
+    let first = fragment(7, 2, 0, b"\xFF\xFF\xFF\xFF\x49\x11");
+    let addr = spawn_scripted(vec![first.clone()]);
+    let client = crowbar_a2s::Builder::new()
+        .timeout(std::time::Duration::from_millis(200))
+        .capture_datagrams(true)
+        .build_async()
+        .unwrap();
+
+    let err = client.info(addr).await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "Info query to {} failed at fragment 1: Socket timed out",
+            addr
+        )
+    );
+
+    let context = query_error(err);
+    assert_eq!(context.stage, Stage::Fragment(1));
+    assert_eq!(context.datagrams, vec![first]);
+}
@@ -0,0 +1,97 @@
+#![cfg(feature = "async")]
+
+mod common;
+
+use crowbar_a2s::Exporter;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+fn exporter() -> Exporter {
+    let client = crowbar_a2s::Builder::new()
+        .timeout(Duration::from_millis(200))
+        .build_async()
+        .unwrap();
+    Exporter::new(client)
+}
+
+async fn get(addr: SocketAddr, path: &str) -> String {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path);
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+#[tokio::test]
+async fn test_exporter_metrics() {
+    let addr = common::spawn_stand_ins(1).await[0];
+    let silent = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let silent = silent.local_addr().unwrap();
+
+    let mut exporter = exporter();
+    exporter.server(addr).server(silent).server(addr);
+    assert_eq!(
+        exporter
+            .metrics()
+            .lines()
+            .filter(|l| l.starts_with("a2s_up"))
+            .count(),
+        2
+    );
+
+    exporter.poll().await;
+    let metrics = exporter.metrics();
+    let labels = format!(
+        "server=\"{}\",map=\"de_dust2\",game=\"Counter-Strike: Global Offensive\",version=\"1.38.7.9\"",
+        addr
+    );
+
+    assert!(
+        metrics.contains("# TYPE a2s_players gauge\n"),
+        "{}",
+        metrics
+    );
+    assert!(metrics.contains(&format!("a2s_up{{server=\"{}\"}} 1\n", addr)));
+    assert!(metrics.contains(&format!("a2s_up{{server=\"{}\"}} 0\n", silent)));
+    assert!(metrics.contains(&format!("a2s_players{{{}}} 2\n", labels)));
+    assert!(metrics.contains(&format!("a2s_max_players{{{}}} 24\n", labels)));
+    assert!(metrics.contains(&format!("a2s_bots{{{}}} 0\n", labels)));
+    assert!(metrics.contains(&format!("a2s_rtt_seconds{{{}}} ", labels)));
+
+    // Down servers only report a2s_up
+    let silent = format!("server=\"{}\"", silent);
+    assert_eq!(metrics.matches(&silent).count(), 1);
+}
+
+#[tokio::test]
+async fn test_exporter_serve() {
+    let addr = common::spawn_stand_ins(1).await[0];
+
+    let mut exporter = exporter();
+    exporter.interval(Duration::from_millis(100)).server(addr);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let http = listener.local_addr().unwrap();
+    tokio::spawn(exporter.serve(listener));
+
+    let response = get(http, "/metrics").await;
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(response.contains("Content-Type: text/plain; version=0.0.4"));
+    assert!(response.contains(&format!("a2s_up{{server=\"{}\"}} 1\n", addr)));
+
+    // Still served after later polls
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    let response = get(http, "/metrics").await;
+    assert!(response.contains("a2s_players{"), "{}", response);
+
+    let response = get(http, "/").await;
+    assert!(
+        response.starts_with("HTTP/1.1 404 Not Found\r\n"),
+        "{}",
+        response
+    );
+}
@@ -0,0 +1,83 @@
+use crowbar_a2s::master::{Condition, Filter};
+
+#[test]
+fn test_filter_render() {
+    let filter = Filter::new()
+        .dedicated(true)
+        .secure(true)
+        .gamedir("cstrike")
+        .linux(true)
+        .password(false)
+        .full(true)
+        .napp(500)
+        .gametype(["tag1", "tag2"])
+        .name_match("*dust*")
+        .nand(Filter::new().map("de_dust2").empty(true))
+        .to_string();
+
+    assert_eq!(
+        filter,
+        "\\dedicated\\1\\secure\\1\\gamedir\\cstrike\\linux\\1\\password\\0\\full\\1\\napp\\500\
+         \\gametype\\tag1,tag2\\name_match\\*dust*\\nand\\2\\map\\de_dust2\\empty\\1"
+    );
+}
+
+#[test]
+fn test_filter_values_escaped() {
+    // Backslashes can't inject conditions of their own
+    let filter = Filter::new()
+        .map("de_dust2\\password\\1")
+        .gametype(["a\\b", "c"])
+        .condition(Condition::Other("x\\y".to_string(), "\\z".to_string()))
+        .to_string();
+
+    assert_eq!(filter, "\\map\\de_dust2password1\\gametype\\ab,c\\xy\\z");
+    assert_eq!(filter.parse::<Filter>().unwrap().conditions().len(), 3);
+}
+
+#[test]
+fn test_filter_parse() {
+    let filter: Filter = "\\appid\\240\\nor\\2\\map\\de_dust2\\gamedataor\\a,b\\white\\1\\foo\\bar"
+        .parse()
+        .unwrap();
+
+    assert_eq!(
+        filter.conditions(),
+        &[
+            Condition::AppId(240),
+            Condition::Nor(vec![
+                Condition::Map("de_dust2".to_string()),
+                Condition::GameDataOr(vec!["a".to_string(), "b".to_string()]),
+            ]),
+            Condition::White(true),
+            Condition::Other("foo".to_string(), "bar".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_filter_roundtrip() {
+    let filter = Filter::new()
+        .appid(730)
+        .empty(true)
+        .noplayers(false)
+        .proxy(false)
+        .gamedata(["coop"])
+        .version_match("1.38.*")
+        .collapse_addr_hash(true)
+        .gameaddr("127.0.0.1:27015")
+        .nor(Filter::new().nand(Filter::new().map("cs_office").full(true)))
+        .clone();
+
+    assert_eq!(filter.to_string().parse::<Filter>().unwrap(), filter);
+    assert_eq!("".parse::<Filter>().unwrap(), Filter::new());
+}
+
+#[test]
+fn test_filter_parse_invalid() {
+    assert!("appid\\240".parse::<Filter>().is_err());
+    assert!("\\appid".parse::<Filter>().is_err());
+    assert!("\\appid\\abc".parse::<Filter>().is_err());
+    assert!("\\secure\\yes".parse::<Filter>().is_err());
+    assert!("\\nor\\2\\map\\de_dust2".parse::<Filter>().is_err());
+}
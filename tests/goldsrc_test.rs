@@ -0,0 +1,129 @@
+use crowbar_a2s::types::info::{ServerOS, ServerType};
+use crowbar_a2s::types::{GoldSrcInfo, GoldSrcMod, ServerInfo};
+use crowbar_a2s::{Engine, Reassembler};
+use std::io::Cursor;
+
+fn goldsrc_info() -> GoldSrcInfo {
+    GoldSrcInfo {
+        address: "127.0.0.1:27015".into(),
+        name: "Counter-Strike 1.6 Server".into(),
+        map: "de_dust2".into(),
+        folder: "cstrike".into(),
+        game: "Counter-Strike".into(),
+        players: 12,
+        max_players: 32,
+        protocol: 47,
+        server_type: ServerType::Dedicated,
+        server_os: ServerOS::Linux,
+        visibility: false,
+        mod_info: Some(GoldSrcMod {
+            link: "http://www.counter-strike.net".into(),
+            download_link: "".into(),
+            version: 1,
+            size: 184000000,
+            multiplayer_only: true,
+            own_dll: true,
+        }),
+        vac: true,
+        bots: 2,
+    }
+}
+
+fn split(id: i32, payload: &[u8], size: usize) -> Vec<Vec<u8>> {
+    let chunks: Vec<&[u8]> = payload.chunks(size).collect();
+    let total = chunks.len() as u8;
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(number, chunk)| {
+            let mut data = Vec::new();
+            data.extend((-2i32).to_le_bytes());
+            data.extend(id.to_le_bytes());
+            data.push(((number as u8) << 4) | total);
+            data.extend(chunk);
+            data
+        })
+        .collect()
+}
+
+#[test]
+fn test_goldsrc_info_roundtrip() {
+    let bytes = goldsrc_info().to_bytes();
+
+    let info = GoldSrcInfo::from_cursor(Cursor::new(bytes[4..].to_vec())).unwrap();
+
+    assert_eq!(info.address, "127.0.0.1:27015");
+    assert_eq!(info.map, "de_dust2");
+    assert_eq!(info.players, 12);
+    assert!(matches!(info.server_type, ServerType::Dedicated));
+    assert!(matches!(info.server_os, ServerOS::Linux));
+    let mod_info = info.mod_info.unwrap();
+    assert_eq!(mod_info.size, 184000000);
+    assert!(mod_info.own_dll);
+    assert!(info.vac);
+    assert_eq!(info.bots, 2);
+}
+
+#[test]
+fn test_listen_server_types() {
+    for byte in [b'i', b'l', b'L'] {
+        assert_eq!(
+            ServerType::try_from(byte).unwrap(),
+            ServerType::NonDedicated
+        );
+    }
+    assert!(ServerType::try_from(b'x').is_err());
+}
+
+#[test]
+fn test_server_info_detection() {
+    let bytes = goldsrc_info().to_bytes();
+
+    let info = ServerInfo::from_cursor(Cursor::new(bytes[4..].to_vec())).unwrap();
+    assert!(matches!(info, ServerInfo::GoldSrc(_)));
+
+    let info = ServerInfo::from_cursor(Cursor::new(vec![0x00]));
+    assert!(info.is_err());
+}
+
+#[test]
+fn test_goldsrc_split_packets() {
+    let bytes = goldsrc_info().to_bytes();
+    let mut reassembler = Reassembler::with_engine(1400, Engine::GoldSrc);
+
+    let mut result = None;
+    for fragment in split(3, &bytes, 16).into_iter().rev() {
+        result = reassembler.push(&fragment).unwrap();
+    }
+
+    assert_eq!(result.unwrap(), &bytes[4..]);
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_goldsrc_client() {
+    use std::net::UdpSocket;
+    use std::thread;
+
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap();
+    let handle = thread::spawn(move || {
+        let mut buf = [0; 1400];
+        let (_, client) = server.recv_from(&mut buf).unwrap();
+        for fragment in split(5, &goldsrc_info().to_bytes(), 32) {
+            server.send_to(&fragment, client).unwrap();
+        }
+    });
+
+    let client = crowbar_a2s::Builder::new()
+        .engine(Engine::GoldSrc)
+        .build_sync()
+        .unwrap();
+    let info = client.server_info(addr).unwrap();
+    handle.join().unwrap();
+
+    match info {
+        ServerInfo::GoldSrc(info) => assert_eq!(info.name, "Counter-Strike 1.6 Server"),
+        ServerInfo::Source(_) => panic!("expected a GoldSrc response"),
+    }
+}
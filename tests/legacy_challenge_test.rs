@@ -0,0 +1,71 @@
+#![cfg(any(feature = "sync", feature = "async"))]
+
+mod common;
+
+use common::StandIn;
+use crowbar_a2s::query::ChallengeMode;
+use crowbar_a2s::server::Responder;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// Spawns a stand-in server ignoring requests with a `-1` challenge, like
+/// servers predating it.
+fn spawn_legacy() -> SocketAddr {
+    let responder = Responder::new(StandIn);
+
+    common::spawn_udp(move |request, client| {
+        if request.len() == 9 && request[5..] == (-1i32).to_le_bytes() {
+            return Vec::new();
+        }
+        responder.respond(request, client).unwrap()
+    })
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_legacy_challenge_sync() {
+    let addr = spawn_legacy();
+    let client = crowbar_a2s::Builder::new()
+        .timeout(Duration::from_millis(200))
+        .build_sync()
+        .unwrap();
+    assert!(client.players(addr).is_err());
+
+    let client = crowbar_a2s::Builder::new()
+        .timeout(Duration::from_millis(200))
+        .challenge_mode(ChallengeMode::Legacy)
+        .build_sync()
+        .unwrap();
+    assert_eq!(client.players(addr).unwrap().len(), 2);
+    assert_eq!(client.rules(addr).unwrap().len(), 2);
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_legacy_challenge_fallback() {
+    use crowbar_a2s::server::A2SServerAsync;
+    use futures::StreamExt;
+
+    let legacy = spawn_legacy();
+    let server = A2SServerAsync::bind("127.0.0.1:0", StandIn).await.unwrap();
+    let current = server.local_addr().unwrap();
+    tokio::spawn(async move { server.run().await });
+
+    let client = crowbar_a2s::Builder::new()
+        .timeout(Duration::from_millis(200))
+        .challenge_mode(ChallengeMode::Fallback)
+        .build_async()
+        .unwrap();
+
+    assert_eq!(client.players(legacy).await.unwrap().len(), 2);
+    assert_eq!(client.rules(current).await.unwrap().len(), 2);
+
+    let results: Vec<_> = client
+        .batch_rules([legacy, current])
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|(_, rules)| rules.is_ok()));
+}
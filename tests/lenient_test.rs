@@ -0,0 +1,148 @@
+mod common;
+
+use crowbar_a2s::errors::Error;
+use crowbar_a2s::types::info::{ExtendedServerInfo, ServerOS, ServerType};
+use crowbar_a2s::types::{Info, Player, Rule, Warning};
+use std::io::Cursor;
+
+/// Payload of a response, without the single packet header
+fn payload(datagram: &[u8]) -> Cursor<Vec<u8>> {
+    Cursor::new(datagram[4..].to_vec())
+}
+
+#[test]
+fn test_unknown_values() {
+    let mut info = common::info();
+    info.server_type = ServerType::Unknown(b'x');
+    info.server_os = ServerOS::Unknown(b'?');
+    let bytes = info.to_bytes();
+
+    assert!(matches!(
+        Info::from_cursor(payload(&bytes)),
+        Err(Error::InvalidField {
+            field: "server_type",
+            ..
+        })
+    ));
+
+    let parsed = Info::from_cursor_lenient(payload(&bytes)).unwrap();
+    assert_eq!(parsed.value.server_type, ServerType::Unknown(b'x'));
+    assert_eq!(parsed.value.server_os, ServerOS::Unknown(b'?'));
+    assert_eq!(
+        parsed.value.extended_server_info.keywords,
+        Some("secure,valve_ds".into())
+    );
+    assert!(!parsed.truncated);
+    assert_eq!(
+        parsed.warnings,
+        vec![
+            Warning::UnknownValue {
+                field: "server_type",
+                value: b'x'
+            },
+            Warning::UnknownValue {
+                field: "server_os",
+                value: b'?'
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_truncated_info() {
+    // Cut within the keywords, before the game id
+    let bytes = common::info().to_bytes();
+    let bytes = &bytes[..bytes.len() - 10];
+
+    assert!(Info::from_cursor(payload(bytes)).is_err());
+
+    let parsed = Info::from_cursor_lenient(payload(bytes)).unwrap();
+    assert!(parsed.truncated);
+    assert_eq!(
+        parsed.warnings,
+        vec![Warning::Truncated { field: "keywords" }]
+    );
+    assert_eq!(parsed.value.version, "1.38.7.9");
+    assert_eq!(parsed.value.extended_server_info.port, Some(27015));
+    assert_eq!(parsed.value.extended_server_info.keywords, None);
+    assert_eq!(parsed.value.extended_server_info.game_id, None);
+}
+
+#[test]
+fn test_unterminated_version() {
+    // Without extra data, the version is the last field
+    let mut info = common::info();
+    info.edf = 0;
+    info.extended_server_info = ExtendedServerInfo {
+        port: None,
+        steam_id: None,
+        keywords: None,
+        game_id: None,
+    };
+    let bytes = info.to_bytes();
+    let bytes = &bytes[..bytes.len() - 1];
+
+    let strict = Info::from_cursor(payload(bytes)).unwrap();
+    assert_eq!(strict.version, "1.38.7.9");
+
+    let parsed = Info::from_cursor_lenient(payload(bytes)).unwrap();
+    assert!(parsed.truncated);
+    assert_eq!(
+        parsed.warnings,
+        vec![Warning::Truncated { field: "version" }]
+    );
+    assert_eq!(parsed.value.version, "");
+}
+
+#[test]
+fn test_truncated_players() {
+    // Cut within the score of the second player
+    let bytes = Player::vec_to_bytes(common::players());
+    let bytes = &bytes[..28];
+
+    assert!(Player::from_cursor(payload(bytes), 0).is_err());
+
+    let parsed = Player::from_cursor_lenient(payload(bytes), 0).unwrap();
+    assert!(parsed.truncated);
+    assert_eq!(parsed.warnings, vec![Warning::Truncated { field: "score" }]);
+    assert_eq!(parsed.value.len(), 1);
+    assert_eq!(parsed.value[0].name, "alice");
+}
+
+#[test]
+fn test_truncated_rules() {
+    // Cut within the name of the second rule
+    let bytes = Rule::vec_to_bytes(common::rules());
+    let bytes = &bytes[..29];
+
+    // Strict parsing reads a string cut short up to the end
+    let rules = Rule::from_cursor(payload(bytes)).unwrap();
+    assert_eq!(rules[0], common::rules()[0]);
+    assert_eq!(rules[1].name, "sv_g");
+
+    let parsed = Rule::from_cursor_lenient(payload(bytes)).unwrap();
+    assert!(parsed.truncated);
+    assert_eq!(parsed.warnings, vec![Warning::Truncated { field: "name" }]);
+    assert_eq!(parsed.value, common::rules()[..1]);
+
+    // Complete responses parse the same either way
+    let bytes = Rule::vec_to_bytes(common::rules());
+    let parsed = Rule::from_cursor_lenient(payload(&bytes)).unwrap();
+    assert!(!parsed.truncated);
+    assert!(parsed.warnings.is_empty());
+    assert_eq!(parsed.value, Rule::from_cursor(payload(&bytes)).unwrap());
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_lenient_client() {
+    let bytes = Rule::vec_to_bytes(common::rules());
+    let addr = common::spawn_udp(move |_, _| vec![bytes[..29].to_vec()]);
+
+    let client = crowbar_a2s::Builder::new().build_sync().unwrap();
+    assert_eq!(client.rules(addr).unwrap()[1].name, "sv_g");
+
+    let parsed = client.rules_lenient(addr).unwrap();
+    assert!(parsed.truncated);
+    assert_eq!(parsed.value, common::rules()[..1]);
+}
@@ -0,0 +1,81 @@
+#![cfg(feature = "async")]
+
+mod common;
+
+use crowbar_a2s::errors::{Error, Stage};
+use futures::StreamExt;
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn test_packets_per_second() {
+    let addrs = common::spawn_stand_ins(10).await;
+    let client = crowbar_a2s::Builder::new()
+        .packets_per_second(10)
+        .build_async()
+        .unwrap();
+
+    // 20 packets: the challenge and the challenged request to each server,
+    // the first 10 of which are a burst
+    let start = Instant::now();
+    let players: Vec<_> = client.batch_players(addrs).await.unwrap().collect().await;
+
+    assert!(start.elapsed() >= Duration::from_millis(800));
+    assert_eq!(players.len(), 10);
+    assert!(players.iter().all(|(_, players)| players.is_ok()));
+}
+
+#[tokio::test]
+async fn test_per_host_limit() {
+    let live = common::spawn_stand_ins(1).await[0];
+    // bound but never answering, on the same IP address
+    let silent = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let silent = silent.local_addr().unwrap();
+
+    let client = crowbar_a2s::Builder::new()
+        .timeout(Duration::from_millis(300))
+        .per_host_limit(1)
+        .build_async()
+        .unwrap();
+    let results: Vec<_> = client
+        .batch_info([silent, live])
+        .await
+        .unwrap()
+        .collect()
+        .await;
+
+    // The live server waits for the silent one to time out
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].0, silent);
+    assert!(matches!(
+        common::query_failure(results[0].1.as_ref().unwrap_err()),
+        (Stage::Fragment(0), Error::ErrTimeout)
+    ));
+    assert_eq!(results[1].0, live);
+    assert!(results[1].1.is_ok());
+}
+
+#[tokio::test]
+async fn test_max_in_flight() {
+    let live = common::spawn_stand_ins(1).await[0];
+    let silent = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let silent = silent.local_addr().unwrap();
+
+    let client = crowbar_a2s::Builder::new()
+        .timeout(Duration::from_millis(300))
+        .max_in_flight(1)
+        .build_async()
+        .unwrap();
+
+    let (silent, live) = tokio::join!(client.info(silent), async {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let start = Instant::now();
+        (client.info(live).await, start.elapsed())
+    });
+
+    assert!(matches!(
+        common::query_failure(&silent.unwrap_err()),
+        (Stage::Fragment(0), Error::ErrTimeout)
+    ));
+    assert!(live.0.is_ok());
+    assert!(live.1 >= Duration::from_millis(200));
+}
@@ -0,0 +1,136 @@
+mod common;
+
+use crowbar_a2s::master::{Filter, MasterQuery, Region, MASTER_RESPONSE_HEADER};
+use std::net::{Ipv4Addr, SocketAddrV4};
+#[cfg(any(feature = "sync", feature = "async"))]
+use std::time::Duration;
+
+fn page(addrs: &[SocketAddrV4]) -> Vec<u8> {
+    let mut data = MASTER_RESPONSE_HEADER.to_vec();
+    for addr in addrs {
+        data.extend(addr.ip().octets());
+        data.extend(addr.port().to_be_bytes());
+    }
+    data
+}
+
+fn servers() -> Vec<SocketAddrV4> {
+    (1..=5)
+        .map(|i| SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, i), 27015))
+        .collect()
+}
+
+/// Answers requests with pages of two servers, terminated with 0.0.0.0:0
+#[cfg(any(feature = "sync", feature = "async"))]
+fn spawn_master(delay: Duration) -> std::net::SocketAddr {
+    let servers = servers();
+    common::spawn_udp(move |request, _| {
+        std::thread::sleep(delay);
+        assert_eq!(request[0], 0x31);
+        assert_eq!(request[1], Region::Europe as u8);
+        let fields: Vec<&[u8]> = request[2..].split(|b| *b == 0).collect();
+        assert_eq!(fields[1], b"\\appid\\240");
+
+        let seed: SocketAddrV4 = std::str::from_utf8(fields[0]).unwrap().parse().unwrap();
+        let start = servers.iter().position(|s| *s == seed).map_or(0, |i| i + 1);
+        let mut addrs: Vec<_> = servers.iter().skip(start).take(2).copied().collect();
+        if start + 2 >= servers.len() {
+            addrs.push(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0));
+        }
+        vec![page(&addrs)]
+    })
+}
+
+#[test]
+fn test_master_query_pagination() {
+    let servers = servers();
+    let mut query = MasterQuery::new(Region::RestOfWorld, &Filter::new());
+
+    assert_eq!(
+        query.next_request().unwrap(),
+        b"\x31\xFF0.0.0.0:0\x00\x00".to_vec()
+    );
+    query.push_response(&page(&servers[..2])).unwrap();
+    assert_eq!(query.pop(), Some(servers[0]));
+    assert_eq!(query.pop(), Some(servers[1]));
+    assert_eq!(query.pop(), None);
+
+    assert_eq!(
+        query.next_request().unwrap(),
+        b"\x31\xFF10.0.0.2:27015\x00\x00".to_vec()
+    );
+    query
+        .push_response(&page(&[
+            servers[2],
+            SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0),
+        ]))
+        .unwrap();
+    assert_eq!(query.pop(), Some(servers[2]));
+    assert!(query.is_done());
+    assert!(query.next_request().is_none());
+}
+
+#[test]
+fn test_master_query_invalid_response() {
+    let mut query = MasterQuery::new(Region::RestOfWorld, &Filter::new());
+
+    assert!(query.push_response(b"\xFF\xFF\xFF\xFF\x66").is_err());
+    assert!(query
+        .push_response(b"\xFF\xFF\xFF\xFF\x66\x0A\x01\x02")
+        .is_err());
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_master_servers_sync() {
+    let master = spawn_master(Duration::ZERO);
+    let client = crowbar_a2s::Builder::new().build_sync().unwrap();
+
+    let result: Vec<_> = client
+        .master_servers(master, Region::Europe, Filter::new().appid(240))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    assert_eq!(result, servers());
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_master_servers_after_query_sync() {
+    let client = crowbar_a2s::Builder::new()
+        .timeout(Duration::from_secs(2))
+        .fragment_timeout(Duration::from_millis(50))
+        .build_sync()
+        .unwrap();
+    client.info(common::spawn_stand_in()).unwrap();
+
+    // The master answers slower than the fragment timeout the query read with
+    let master = spawn_master(Duration::from_millis(200));
+    let result: Vec<_> = client
+        .master_servers(master, Region::Europe, Filter::new().appid(240))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    assert_eq!(result, servers());
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_master_servers_async() {
+    use futures::TryStreamExt;
+
+    let master = spawn_master(Duration::ZERO);
+    let client = crowbar_a2s::Builder::new().build_async().unwrap();
+
+    let result: Vec<_> = client
+        .master_servers(master, Region::Europe, Filter::new().appid(240))
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+
+    assert_eq!(result, servers());
+}
@@ -0,0 +1,80 @@
+#![cfg(any(feature = "sync", feature = "async"))]
+
+mod common;
+
+use common::StandIn;
+use crowbar_a2s::server::Responder;
+use crowbar_a2s::types::PING_REQUEST;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// Spawns a stand-in server answering `delay` late, which ignores A2A_PING
+/// unless `ping` is set.
+fn spawn_delayed(delay: Duration, ping: bool) -> SocketAddr {
+    let mut responder = Responder::new(StandIn);
+    responder.info_challenge(true);
+
+    common::spawn_udp(move |request, client| {
+        if !ping && request == PING_REQUEST {
+            return Vec::new();
+        }
+        std::thread::sleep(delay);
+        responder.respond(request, client).unwrap()
+    })
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_ping_sync() {
+    let client = crowbar_a2s::Builder::new()
+        .timeout(Duration::from_millis(300))
+        .build_sync()
+        .unwrap();
+
+    let rtt = client.ping(spawn_delayed(Duration::from_millis(50), true));
+    assert!(rtt.unwrap() >= Duration::from_millis(50));
+
+    let addr = spawn_delayed(Duration::from_millis(50), false);
+    assert!(client.ping(addr).is_err());
+    assert!(client.latency(addr).unwrap() >= Duration::from_millis(50));
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_timed_sync() {
+    let client = crowbar_a2s::Builder::new().build_sync().unwrap();
+    let addr = spawn_delayed(Duration::from_millis(50), true);
+
+    // The challenge round-trip is not part of the RTT
+    let info = client.info_timed(addr).unwrap();
+    assert_eq!(info.value.map, "de_dust2");
+    assert!(info.rtt >= Duration::from_millis(50));
+    assert!(info.rtt < Duration::from_millis(100));
+
+    let players = client.players_timed(addr).unwrap();
+    assert_eq!(players.value.len(), 2);
+    assert!(players.rtt >= Duration::from_millis(50));
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_ping_async() {
+    let client = crowbar_a2s::Builder::new()
+        .timeout(Duration::from_millis(300))
+        .build_async()
+        .unwrap();
+
+    let rtt = client
+        .ping(spawn_delayed(Duration::from_millis(50), true))
+        .await;
+    assert!(rtt.unwrap() >= Duration::from_millis(50));
+
+    let addr = spawn_delayed(Duration::from_millis(50), false);
+    assert!(client.ping(addr).await.is_err());
+    assert!(client.latency(addr).await.unwrap() >= Duration::from_millis(50));
+
+    let rules = client.rules_timed(addr).await.unwrap();
+    assert_eq!(rules.value.len(), 2);
+    assert!(rules.rtt >= Duration::from_millis(50));
+    assert!(rules.rtt < Duration::from_millis(100));
+}
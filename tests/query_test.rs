@@ -0,0 +1,116 @@
+use crowbar_a2s::errors::Error;
+use crowbar_a2s::query::{
+    ChallengeCache, ChallengeMode, Query, QueryKind, QueryStatus, GETCHALLENGE_REQUEST,
+};
+use crowbar_a2s::types::{INFO_REQUEST, PLAYER_REQUEST};
+use crowbar_a2s::Engine;
+use std::time::Duration;
+
+#[test]
+fn test_query_challenge() {
+    let mut query = Query::new(QueryKind::Players, 1400, Engine::Source);
+
+    assert_eq!(&query.request()[..5], &PLAYER_REQUEST);
+    assert_eq!(&query.request()[5..], &(-1i32).to_le_bytes());
+
+    let status = query.push(b"\xFF\xFF\xFF\xFFA\x01\x02\x03\x04").unwrap();
+    assert_eq!(status, QueryStatus::Resend);
+    assert_eq!(&query.request()[5..], b"\x01\x02\x03\x04");
+
+    let status = query.push(b"\xFF\xFF\xFF\xFFD\x00").unwrap();
+    assert_eq!(status, QueryStatus::Complete(b"D\x00".to_vec()));
+}
+
+#[test]
+fn test_query_info_without_challenge() {
+    let mut query = Query::new(QueryKind::Info, 1400, Engine::Source);
+
+    assert_eq!(query.request(), &INFO_REQUEST);
+
+    let status = query.push(b"\xFF\xFF\xFF\xFFI\x11").unwrap();
+    assert_eq!(status, QueryStatus::Complete(b"I\x11".to_vec()));
+}
+
+#[test]
+fn test_query_challenge_loop() {
+    let mut query = Query::new(QueryKind::Info, 1400, Engine::Source);
+
+    for _ in 0..3 {
+        let status = query.push(b"\xFF\xFF\xFF\xFFA\x01\x02\x03\x04").unwrap();
+        assert_eq!(status, QueryStatus::Resend);
+        assert_eq!(&query.request()[INFO_REQUEST.len()..], b"\x01\x02\x03\x04");
+    }
+
+    let result = query.push(b"\xFF\xFF\xFF\xFFA\x01\x02\x03\x04");
+    assert!(matches!(result, Err(Error::InvalidResponse)));
+}
+
+#[test]
+fn test_query_cached_challenge() {
+    let mut query = Query::new(QueryKind::Rules, 1400, Engine::Source);
+    query.use_challenge(0x04030201);
+
+    assert_eq!(query.challenge(), Some(0x04030201));
+    assert_eq!(&query.request()[5..], b"\x01\x02\x03\x04");
+
+    // A stale challenge gets replaced
+    let status = query.push(b"\xFF\xFF\xFF\xFFA\x05\x06\x07\x08").unwrap();
+    assert_eq!(status, QueryStatus::Resend);
+    assert_eq!(query.challenge(), Some(0x08070605));
+}
+
+#[test]
+fn test_challenge_cache() {
+    let server = "127.0.0.1:27015".parse().unwrap();
+    let cache = ChallengeCache::new(Duration::from_millis(50));
+    assert_eq!(cache.get(server), None);
+
+    let mut query = Query::new(QueryKind::Players, 1400, Engine::Source);
+    query.push(b"\xFF\xFF\xFF\xFFA\x01\x02\x03\x04").unwrap();
+    cache.update(server, &query);
+    assert_eq!(cache.get(server), Some(0x04030201));
+
+    let mut query = Query::new(QueryKind::Rules, 1400, Engine::Source);
+    cache.prepare(server, &mut query);
+    assert_eq!(&query.request()[5..], b"\x01\x02\x03\x04");
+
+    std::thread::sleep(Duration::from_millis(60));
+    assert_eq!(cache.get(server), None);
+}
+
+#[test]
+fn test_query_legacy_challenge() {
+    let mut query = Query::new(QueryKind::Players, 1400, Engine::Source);
+    query.challenge_mode(ChallengeMode::Legacy);
+    assert_eq!(query.request(), &GETCHALLENGE_REQUEST);
+
+    let status = query.push(b"\xFF\xFF\xFF\xFFA\x01\x02\x03\x04").unwrap();
+    assert_eq!(status, QueryStatus::Resend);
+    assert_eq!(&query.request()[..5], &PLAYER_REQUEST);
+    assert_eq!(&query.request()[5..], b"\x01\x02\x03\x04");
+
+    // A2S_INFO has no standalone challenge request
+    let mut query = Query::new(QueryKind::Info, 1400, Engine::Source);
+    query.challenge_mode(ChallengeMode::Legacy);
+    assert_eq!(query.request(), &INFO_REQUEST);
+}
+
+#[test]
+fn test_query_fallback() {
+    let mut query = Query::new(QueryKind::Rules, 1400, Engine::Source);
+    query.challenge_mode(ChallengeMode::Fallback);
+    assert_eq!(&query.request()[5..], &(-1i32).to_le_bytes());
+
+    assert!(query.fallback());
+    assert_eq!(query.request(), &GETCHALLENGE_REQUEST);
+    assert!(!query.fallback());
+
+    // Servers which issued a challenge understood the request
+    let mut query = Query::new(QueryKind::Rules, 1400, Engine::Source);
+    query.challenge_mode(ChallengeMode::Fallback);
+    query.push(b"\xFF\xFF\xFF\xFFA\x01\x02\x03\x04").unwrap();
+    assert!(!query.fallback());
+
+    let mut query = Query::new(QueryKind::Rules, 1400, Engine::Source);
+    assert!(!query.fallback());
+}
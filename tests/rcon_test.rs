@@ -0,0 +1,250 @@
+#![cfg(feature = "rcon")]
+
+mod common;
+
+use crowbar_a2s::rcon::{
+    Packet, PacketReader, SERVERDATA_AUTH_RESPONSE, SERVERDATA_EXECCOMMAND,
+    SERVERDATA_RESPONSE_VALUE,
+};
+
+#[test]
+fn test_packet_framing() {
+    let packet = Packet::new(7, SERVERDATA_EXECCOMMAND, b"status");
+    let bytes = packet.to_bytes();
+
+    assert_eq!(&bytes[..4], &16i32.to_le_bytes());
+    assert_eq!(&bytes[4..8], &7i32.to_le_bytes());
+    assert_eq!(&bytes[8..12], &2i32.to_le_bytes());
+    assert_eq!(&bytes[12..], b"status\0\0");
+    assert_eq!(Packet::from_bytes(&bytes).unwrap(), packet);
+}
+
+#[test]
+fn test_packet_reader() {
+    let first = Packet::new(1, SERVERDATA_RESPONSE_VALUE, b"hello");
+    let second = Packet::new(2, SERVERDATA_AUTH_RESPONSE, b"");
+    let mut stream = first.to_bytes();
+    stream.extend(second.to_bytes());
+
+    let mut reader = PacketReader::new();
+    reader.extend(&stream[..3]);
+    assert_eq!(reader.next_packet().unwrap(), None);
+    reader.extend(&stream[3..20]);
+    assert_eq!(reader.next_packet().unwrap(), Some(first));
+    assert_eq!(reader.next_packet().unwrap(), None);
+    reader.extend(&stream[20..]);
+    assert_eq!(reader.next_packet().unwrap(), Some(second));
+
+    let mut reader = PacketReader::new();
+    reader.extend(&(-5i32).to_le_bytes());
+    assert!(reader.next_packet().is_err());
+}
+
+/// Behaves like srcds: long output is split over several packets, and the
+/// mirrored empty packet is followed by an extra `0x01` packet.
+#[cfg(any(feature = "sync", feature = "async"))]
+fn spawn_rcon_server() -> std::net::SocketAddr {
+    use crowbar_a2s::rcon::SERVERDATA_AUTH;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            std::thread::spawn(move || {
+                let mut reader = PacketReader::new();
+                let mut data = [0; 4096];
+                loop {
+                    let packet = match reader.next_packet().unwrap() {
+                        Some(packet) => packet,
+                        None => {
+                            let read = stream.read(&mut data).unwrap();
+                            if read == 0 {
+                                return;
+                            }
+                            reader.extend(&data[..read]);
+                            continue;
+                        }
+                    };
+
+                    let mut response = Vec::new();
+                    match packet.kind {
+                        SERVERDATA_AUTH => {
+                            let id = if packet.body == b"secret" {
+                                packet.id
+                            } else {
+                                -1
+                            };
+                            response
+                                .extend(Packet::new(id, SERVERDATA_RESPONSE_VALUE, b"").to_bytes());
+                            response
+                                .extend(Packet::new(id, SERVERDATA_AUTH_RESPONSE, b"").to_bytes());
+                        }
+                        SERVERDATA_EXECCOMMAND => {
+                            let output = format!("{}:{}", "x".repeat(5000), packet.body.len());
+                            for chunk in output.as_bytes().chunks(4000) {
+                                response.extend(
+                                    Packet::new(packet.id, SERVERDATA_RESPONSE_VALUE, chunk)
+                                        .to_bytes(),
+                                );
+                            }
+                        }
+                        _ => {
+                            response.extend(
+                                Packet::new(packet.id, SERVERDATA_RESPONSE_VALUE, b"").to_bytes(),
+                            );
+                            response.extend(
+                                Packet::new(
+                                    packet.id,
+                                    SERVERDATA_RESPONSE_VALUE,
+                                    b"\x00\x01\x00\x00",
+                                )
+                                .to_bytes(),
+                            );
+                        }
+                    }
+                    // dribble the response to exercise reassembly of frames
+                    for chunk in response.chunks(1000) {
+                        stream.write_all(chunk).unwrap();
+                    }
+                }
+            });
+        }
+    });
+    addr
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_rcon_sync() {
+    let addr = spawn_rcon_server();
+    let builder = crowbar_a2s::Builder::new();
+
+    let mut rcon = builder.connect_rcon(addr, "secret").unwrap();
+    let output = rcon.exec("status").unwrap();
+    assert_eq!(output, format!("{}:6", "x".repeat(5000)));
+    let output = rcon.exec("users").unwrap();
+    assert_eq!(output, format!("{}:5", "x".repeat(5000)));
+
+    let result = builder.connect_rcon(addr, "wrong");
+    assert!(matches!(result, Err(crowbar_a2s::errors::Error::RconAuth)));
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_rcon_async() {
+    let addr = spawn_rcon_server();
+    let builder = crowbar_a2s::Builder::new();
+
+    let mut rcon = builder.connect_rcon_async(addr, "secret").await.unwrap();
+    let output = rcon.exec("status").await.unwrap();
+    assert_eq!(output, format!("{}:6", "x".repeat(5000)));
+    let output = rcon.exec("maps *").await.unwrap();
+    assert_eq!(output, format!("{}:6", "x".repeat(5000)));
+
+    let result = builder.connect_rcon_async(addr, "wrong").await;
+    assert!(matches!(result, Err(crowbar_a2s::errors::Error::RconAuth)));
+}
+
+mod goldsrc {
+    use crowbar_a2s::errors::Error;
+    use crowbar_a2s::rcon::goldsrc::{
+        command_request, parse_challenge, parse_response, parse_responses,
+    };
+
+    #[test]
+    fn test_goldsrc_messages() {
+        assert_eq!(
+            parse_challenge(b"challenge rcon 3056237345\n\0").unwrap(),
+            "3056237345"
+        );
+        assert!(parse_challenge(b"challenge rcon \n").is_err());
+        assert!(parse_challenge(b"lsomething else").is_err());
+
+        assert_eq!(
+            command_request("3056237345", "secret", "status").unwrap(),
+            b"\xFF\xFF\xFF\xFFrcon 3056237345 \"secret\" status\n".to_vec()
+        );
+        // Either would change the command line
+        assert!(command_request("3056237345", "se\"cret", "status").is_err());
+        assert!(command_request("3056237345", "secret\nquit", "status").is_err());
+
+        assert_eq!(
+            parse_response(b"lhostname: test\n\0").unwrap(),
+            "hostname: test\n"
+        );
+        assert!(matches!(
+            parse_response(b"lBad rcon_password.\n\0"),
+            Err(Error::RconAuth)
+        ));
+        assert!(parse_response(b"A1234").is_err());
+
+        let responses = [
+            b"lhostname: test\n\0".to_vec(),
+            b"lmap: de_dust2\n\0".to_vec(),
+        ];
+        assert_eq!(
+            parse_responses(&responses).unwrap(),
+            "hostname: test\nmap: de_dust2\n"
+        );
+    }
+
+    /// Behaves like HLDS, splitting long output with the GoldSrc header, and
+    /// sending the end of it as a second print response
+    #[cfg(any(feature = "sync", feature = "async"))]
+    pub fn spawn_hlds() -> std::net::SocketAddr {
+        use crowbar_a2s::packet::Encoder;
+        use crowbar_a2s::Engine;
+        let mut encoder = Encoder::new(1400);
+        encoder.engine(Engine::GoldSrc);
+
+        crate::common::spawn_udp(move |request, _| {
+            let request = String::from_utf8_lossy(&request[4..]).to_string();
+
+            let mut response = b"\xFF\xFF\xFF\xFF".to_vec();
+            if request == "challenge rcon\n" {
+                response.extend(b"challenge rcon 1234567\n\0");
+            } else if request.starts_with("rcon 1234567 \"secret\" ") {
+                response.push(b'l');
+                response.extend("y".repeat(3000).as_bytes());
+                response.push(0);
+
+                let mut datagrams = encoder.encode(1, &response).unwrap();
+                let mut end = b"\xFF\xFF\xFF\xFFl".to_vec();
+                end.extend(request.trim_end().rsplit(' ').next().unwrap().as_bytes());
+                end.push(0);
+                datagrams.push(end);
+                return datagrams;
+            } else {
+                response.extend(b"lBad rcon_password.\n\0");
+            }
+
+            encoder.encode(1, &response).unwrap()
+        })
+    }
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_goldsrc_rcon_sync() {
+    let addr = goldsrc::spawn_hlds();
+    let client = crowbar_a2s::Builder::new().build_sync().unwrap();
+
+    let output = client.goldsrc_rcon(addr, "secret", "status").unwrap();
+    assert_eq!(output, format!("{}status", "y".repeat(3000)));
+
+    let result = client.goldsrc_rcon(addr, "wrong", "status");
+    assert!(matches!(result, Err(crowbar_a2s::errors::Error::RconAuth)));
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_goldsrc_rcon_async() {
+    let addr = goldsrc::spawn_hlds();
+    let client = crowbar_a2s::Builder::new().build_async().unwrap();
+
+    let output = client.goldsrc_rcon(addr, "secret", "users").await.unwrap();
+    assert_eq!(output, format!("{}users", "y".repeat(3000)));
+}
@@ -0,0 +1,130 @@
+use bzip2::write::BzEncoder;
+use bzip2::Compression;
+use crowbar_a2s::errors::Error;
+use crowbar_a2s::{Reassembler, CRC32};
+use std::io::Write;
+
+const RESPONSE: &[u8] = b"\xFF\xFF\xFF\xFFEsome rules payload that is split up";
+
+fn fragment(id: i32, total: u8, number: u8, header: &[u8], payload: &[u8]) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend((-2i32).to_le_bytes());
+    data.extend(id.to_le_bytes());
+    data.push(total);
+    data.push(number);
+    data.extend(1248u16.to_le_bytes());
+    data.extend(header);
+    data.extend(payload);
+    data
+}
+
+#[test]
+fn test_single_packet() {
+    let mut reassembler = Reassembler::new(1400);
+
+    let payload = reassembler
+        .push(b"\xFF\xFF\xFF\xFFA\x01\x02\x03\x04")
+        .unwrap();
+
+    assert_eq!(payload.unwrap(), b"A\x01\x02\x03\x04");
+    assert!(!reassembler.is_pending());
+}
+
+#[test]
+fn test_multi_packet_out_of_order() {
+    let mut reassembler = Reassembler::new(1400);
+    let chunks: Vec<&[u8]> = RESPONSE.chunks(10).collect();
+    let total = chunks.len() as u8;
+
+    for (number, chunk) in chunks.iter().enumerate().rev() {
+        let result = reassembler
+            .push(&fragment(7, total, number as u8, &[], chunk))
+            .unwrap();
+
+        if number == 0 {
+            assert_eq!(result.unwrap(), &RESPONSE[4..]);
+        } else {
+            assert!(result.is_none());
+            assert!(reassembler.is_pending());
+        }
+    }
+}
+
+#[test]
+fn test_multi_packet_bz2() {
+    let mut encoder = BzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(RESPONSE).unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    let mut header = Vec::new();
+    header.extend((RESPONSE.len() as u32).to_le_bytes());
+    header.extend(CRC32.checksum(RESPONSE).to_le_bytes());
+
+    let id = 0x80000001u32 as i32;
+    let (first, second) = compressed.split_at(compressed.len() / 2);
+    let mut reassembler = Reassembler::new(1400);
+
+    assert!(reassembler
+        .push(&fragment(id, 2, 1, &[], second))
+        .unwrap()
+        .is_none());
+    let payload = reassembler
+        .push(&fragment(id, 2, 0, &header, first))
+        .unwrap();
+
+    assert_eq!(payload.unwrap(), &RESPONSE[4..]);
+}
+
+#[test]
+fn test_multi_packet_bz2_checksum_mismatch() {
+    let mut encoder = BzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(RESPONSE).unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    let mut header = Vec::new();
+    header.extend((RESPONSE.len() as u32).to_le_bytes());
+    header.extend(0xDEADBEEFu32.to_le_bytes());
+
+    let mut reassembler = Reassembler::new(1400);
+    let result = reassembler.push(&fragment(0x80000001u32 as i32, 1, 0, &header, &compressed));
+
+    assert!(matches!(result, Err(Error::CheckSumMismatch)));
+}
+
+#[test]
+fn test_mismatch_id() {
+    let mut reassembler = Reassembler::new(1400);
+
+    assert!(reassembler
+        .push(&fragment(1, 2, 0, &[], &RESPONSE[..10]))
+        .unwrap()
+        .is_none());
+    let result = reassembler.push(&fragment(2, 2, 1, &[], &RESPONSE[10..]));
+
+    assert!(matches!(result, Err(Error::MismatchID)));
+    assert!(!reassembler.is_pending());
+}
+
+#[test]
+fn test_invalid_responses() {
+    let mut reassembler = Reassembler::new(1400);
+
+    assert!(matches!(
+        reassembler.push(b"\xFF\xFF"),
+        Err(Error::InvalidResponse)
+    ));
+    assert!(matches!(
+        reassembler.push(b"\x00\x00\x00\x00I"),
+        Err(Error::InvalidResponse)
+    ));
+    // fragment number out of range
+    assert!(matches!(
+        reassembler.push(&fragment(1, 2, 2, &[], RESPONSE)),
+        Err(Error::InvalidResponse)
+    ));
+    // too many fragments
+    assert!(matches!(
+        reassembler.push(&fragment(1, 33, 0, &[], RESPONSE)),
+        Err(Error::InvalidResponse)
+    ));
+}
@@ -0,0 +1,186 @@
+mod common;
+
+#[cfg(any(feature = "sync", feature = "async"))]
+use common::StandIn;
+#[cfg(any(feature = "sync", feature = "async"))]
+use crowbar_a2s::errors::{Error, Stage};
+#[cfg(any(feature = "sync", feature = "async"))]
+use crowbar_a2s::server::Responder;
+use crowbar_a2s::RetryPolicy;
+#[cfg(any(feature = "sync", feature = "async"))]
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// Spawns a stand-in server which ignores the first `lost` requests, as if
+/// they got lost on the way.
+#[cfg(any(feature = "sync", feature = "async"))]
+fn spawn_lossy(lost: usize) -> SocketAddr {
+    let responder = Responder::new(StandIn);
+    let mut received = 0;
+
+    common::spawn_udp(move |request, client| {
+        received += 1;
+        if received <= lost {
+            return Vec::new();
+        }
+        responder.respond(request, client).unwrap()
+    })
+}
+
+#[cfg(any(feature = "sync", feature = "async"))]
+fn policy(attempts: u32) -> RetryPolicy {
+    RetryPolicy {
+        attempts,
+        base_delay: Duration::from_millis(10),
+        ..RetryPolicy::default()
+    }
+}
+
+#[test]
+fn test_retry_delay() {
+    let policy = RetryPolicy {
+        base_delay: Duration::from_millis(100),
+        jitter: 0.0,
+        ..RetryPolicy::default()
+    };
+    assert_eq!(policy.delay(1), Duration::from_millis(100));
+    assert_eq!(policy.delay(2), Duration::from_millis(200));
+    assert_eq!(policy.delay(3), Duration::from_millis(400));
+
+    let policy = RetryPolicy {
+        jitter: 0.5,
+        ..policy
+    };
+    for _ in 0..100 {
+        let delay = policy.delay(2);
+        assert!(delay >= Duration::from_millis(200));
+        assert!(delay <= Duration::from_millis(300));
+    }
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_retry_sync() {
+    let client = crowbar_a2s::Builder::new()
+        .timeout(Duration::from_millis(100))
+        .retry(policy(3))
+        .build_sync()
+        .unwrap();
+
+    let info = client.info(spawn_lossy(1)).unwrap();
+    assert_eq!(info.map, "de_dust2");
+
+    // Both the challenge request and its retry get lost
+    let players = client.players(spawn_lossy(2)).unwrap();
+    assert_eq!(players.len(), 2);
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_retry_exhausted_sync() {
+    let client = crowbar_a2s::Builder::new()
+        .timeout(Duration::from_millis(100))
+        .retry(policy(3))
+        .build_sync()
+        .unwrap();
+
+    match client.info(spawn_lossy(3)) {
+        Err(Error::RetriesExhausted { attempts, source }) => {
+            assert_eq!(attempts, 3);
+            assert!(matches!(
+                common::query_failure(&source),
+                (Stage::Fragment(0), Error::Io(_))
+            ));
+        }
+        result => panic!("unexpected {:?}", result),
+    }
+
+    // Without a policy, timeouts are reported with the query they ended
+    let client = crowbar_a2s::Builder::new()
+        .timeout(Duration::from_millis(100))
+        .build_sync()
+        .unwrap();
+    let err = client.info(spawn_lossy(1)).unwrap_err();
+    assert!(matches!(
+        common::query_failure(&err),
+        (Stage::Fragment(0), Error::Io(_))
+    ));
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_retry_async() {
+    let client = crowbar_a2s::Builder::new()
+        .timeout(Duration::from_millis(100))
+        .retry(policy(3))
+        .build_async()
+        .unwrap();
+
+    let players = client.players(spawn_lossy(2)).await.unwrap();
+    assert_eq!(players.len(), 2);
+
+    match client.rules(spawn_lossy(3)).await {
+        Err(Error::RetriesExhausted { attempts, source }) => {
+            assert_eq!(attempts, 3);
+            assert!(matches!(
+                common::query_failure(&source),
+                (Stage::Fragment(0), Error::ErrTimeout)
+            ));
+        }
+        result => panic!("unexpected {:?}", result),
+    }
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_retry_deadline() {
+    let client = crowbar_a2s::Builder::new()
+        .timeout(Duration::from_millis(100))
+        .retry(RetryPolicy {
+            deadline: Some(Duration::from_millis(250)),
+            ..policy(10)
+        })
+        .build_async()
+        .unwrap();
+
+    let start = std::time::Instant::now();
+    match client.info(spawn_lossy(10)).await {
+        Err(Error::RetriesExhausted { attempts, .. }) => assert!(attempts < 4),
+        result => panic!("unexpected {:?}", result),
+    }
+    assert!(start.elapsed() < Duration::from_millis(500));
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_retry_deadline_cuts_attempt() {
+    let client = crowbar_a2s::Builder::new()
+        .timeout(Duration::from_secs(2))
+        .retry(RetryPolicy {
+            deadline: Some(Duration::from_millis(200)),
+            ..policy(3)
+        })
+        .build_sync()
+        .unwrap();
+
+    let start = std::time::Instant::now();
+    assert!(client.info(spawn_lossy(10)).is_err());
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_retry_batch() {
+    use futures::StreamExt;
+
+    let addrs: Vec<_> = (0..10).map(|_| spawn_lossy(1)).collect();
+    let client = crowbar_a2s::Builder::new()
+        .timeout(Duration::from_millis(100))
+        .retry(policy(2))
+        .build_async()
+        .unwrap();
+
+    let results: Vec<_> = client.batch_rules(addrs).await.unwrap().collect().await;
+    assert_eq!(results.len(), 10);
+    assert!(results.iter().all(|(_, rules)| rules.is_ok()));
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use common::StandIn;
+use crowbar_a2s::query::GETCHALLENGE_REQUEST;
+use crowbar_a2s::server::Responder;
+use crowbar_a2s::types::{Info, Player, Rule, INFO_REQUEST, PLAYER_REQUEST};
+use std::io::Cursor;
+use std::net::SocketAddr;
+
+#[test]
+fn test_responder_challenge() {
+    let responder = Responder::new(StandIn);
+    let client: SocketAddr = "127.0.0.1:50000".parse().unwrap();
+    let challenge = responder.challenge(client);
+
+    let response = responder.respond(&INFO_REQUEST, client).unwrap().remove(0);
+    assert_eq!(response[4], b'A');
+    assert_eq!(&response[5..], &challenge.to_le_bytes());
+
+    let mut request = INFO_REQUEST.to_vec();
+    request.extend(challenge.to_le_bytes());
+    let response = responder.respond(&request, client).unwrap().remove(0);
+    let info = Info::from_cursor(Cursor::new(response[4..].to_vec())).unwrap();
+    assert_eq!(info.name, "Stand-in Server");
+
+    let mut request = PLAYER_REQUEST.to_vec();
+    request.extend((-1i32).to_le_bytes());
+    let response = responder.respond(&request, client).unwrap().remove(0);
+    assert_eq!(response[4], b'A');
+
+    // challenges are bound to the client address
+    let other: SocketAddr = "127.0.0.2:50000".parse().unwrap();
+    let mut request = PLAYER_REQUEST.to_vec();
+    request.extend(challenge.to_le_bytes());
+    let response = responder.respond(&request, other).unwrap().remove(0);
+    assert_eq!(response[4], b'A');
+
+    let response = responder.respond(&request, client).unwrap().remove(0);
+    let players = Player::from_cursor(Cursor::new(response[4..].to_vec()), 0).unwrap();
+    assert_eq!(players.len(), 2);
+
+    assert!(responder
+        .respond(b"\xFF\xFF\xFF\xFFZ", client)
+        .unwrap()
+        .is_empty());
+    assert!(responder.respond(b"garbage", client).unwrap().is_empty());
+}
+
+#[test]
+fn test_responder_without_info_challenge() {
+    let mut responder = Responder::new(StandIn);
+    responder.info_challenge(false);
+    let client: SocketAddr = "127.0.0.1:50000".parse().unwrap();
+
+    let response = responder.respond(&INFO_REQUEST, client).unwrap().remove(0);
+    assert_eq!(response[4], 0x49);
+}
+
+#[test]
+fn test_responder_legacy_challenge() {
+    let responder = Responder::new(StandIn);
+    let client: SocketAddr = "127.0.0.1:50000".parse().unwrap();
+
+    let response = responder
+        .respond(&GETCHALLENGE_REQUEST, client)
+        .unwrap()
+        .remove(0);
+    assert_eq!(response[4], b'A');
+    assert_eq!(&response[5..], &responder.challenge(client).to_le_bytes());
+}
+
+#[test]
+fn test_rules_roundtrip() {
+    let bytes = Rule::vec_to_bytes(common::rules());
+    let rules = Rule::from_cursor(Cursor::new(bytes[4..].to_vec())).unwrap();
+
+    assert_eq!(rules.len(), 2);
+    assert_eq!(rules[1].name, "sv_gravity");
+    assert_eq!(rules[1].value, "800");
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_server_sync() {
+    use crowbar_a2s::server::A2SServer;
+
+    let server = A2SServer::bind("127.0.0.1:0", StandIn).unwrap();
+    let addr = server.local_addr().unwrap();
+    std::thread::spawn(move || server.run());
+
+    let client = crowbar_a2s::Builder::new().build_sync().unwrap();
+    let info = client.info(addr).unwrap();
+    let players = client.players(addr).unwrap();
+    let rules = client.rules(addr).unwrap();
+
+    assert_eq!(info.map, "de_dust2");
+    assert_eq!(info.extended_server_info.port, Some(27015));
+    assert_eq!(players[0].name, "alice");
+    assert_eq!(players[1].score, 3);
+    assert_eq!(rules[0].name, "mp_friendlyfire");
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_server_async() {
+    use crowbar_a2s::server::A2SServerAsync;
+
+    let server = A2SServerAsync::bind("127.0.0.1:0", StandIn).await.unwrap();
+    let addr = server.local_addr().unwrap();
+    tokio::spawn(async move { server.run().await });
+
+    let client = crowbar_a2s::Builder::new().build_async().unwrap();
+    let (info, players, rules) =
+        tokio::try_join!(client.info(addr), client.players(addr), client.rules(addr)).unwrap();
+
+    assert_eq!(info.game, "Counter-Strike: Global Offensive");
+    assert_eq!(players.len(), 2);
+    assert_eq!(rules[1].value, "800");
+}
@@ -0,0 +1,91 @@
+#![cfg(any(feature = "sync", feature = "async"))]
+
+mod common;
+
+use common::StandIn;
+use crowbar_a2s::server::Responder;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Spawns a stand-in server challenging A2S_INFO and counting the requests
+/// it receives. Requests of type `ignored` are not answered.
+fn spawn_server(ignored: Option<u8>) -> (SocketAddr, Arc<AtomicUsize>) {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+    let mut responder = Responder::new(StandIn);
+    responder.info_challenge(true);
+
+    let addr = common::spawn_udp(move |request, client| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        if request.len() > 4 && Some(request[4]) == ignored {
+            return Vec::new();
+        }
+        responder.respond(request, client).unwrap()
+    });
+
+    (addr, requests)
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_snapshot_sync() {
+    let (addr, requests) = spawn_server(None);
+    let client = crowbar_a2s::Builder::new().build_sync().unwrap();
+
+    let snapshot = client.snapshot(addr).unwrap();
+    assert_eq!(snapshot.info.map, "de_dust2");
+    assert_eq!(snapshot.players.unwrap().len(), 2);
+    assert_eq!(snapshot.rules.unwrap().len(), 2);
+    assert!(snapshot.queried_at <= std::time::SystemTime::now());
+
+    // Only A2S_INFO asks for a challenge
+    assert_eq!(requests.load(Ordering::SeqCst), 4);
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_snapshot_partial_sync() {
+    // A2S_RULES disabled
+    let (addr, _) = spawn_server(Some(0x56));
+    let client = crowbar_a2s::Builder::new()
+        .timeout(Duration::from_millis(200))
+        .build_sync()
+        .unwrap();
+
+    let snapshot = client.snapshot(addr).unwrap();
+    assert_eq!(snapshot.players.unwrap().len(), 2);
+    assert!(snapshot.players_error.is_none());
+    assert!(snapshot.rules.is_none());
+    assert!(snapshot.rules_error.is_some());
+
+    // Without A2S_INFO, there's no snapshot
+    let (addr, _) = spawn_server(Some(0x54));
+    assert!(client.snapshot(addr).is_err());
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_snapshot_async() {
+    let (addr, requests) = spawn_server(None);
+    let client = crowbar_a2s::Builder::new().build_async().unwrap();
+
+    let snapshot = client.snapshot(addr).await.unwrap();
+    assert_eq!(snapshot.info.name, "Stand-in Server");
+    assert_eq!(snapshot.players.unwrap().len(), 2);
+    assert_eq!(snapshot.rules.unwrap()[1].name, "sv_gravity");
+    assert_eq!(requests.load(Ordering::SeqCst), 4);
+
+    // A2S_PLAYER disabled
+    let (addr, _) = spawn_server(Some(0x55));
+    let client = crowbar_a2s::Builder::new()
+        .timeout(Duration::from_millis(200))
+        .build_async()
+        .unwrap();
+    let snapshot = client.snapshot(addr).await.unwrap();
+    assert!(snapshot.players.is_none());
+    assert!(snapshot.players_error.is_some());
+    assert_eq!(snapshot.rules.unwrap().len(), 2);
+    assert!(snapshot.rules_error.is_none());
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use crowbar_a2s::types::{Info, Player, Rule, Text};
+use std::io::Cursor;
+
+/// "Арма" in Windows-1251
+const CP1251: &[u8] = b"\xC0\xF0\xEC\xE0";
+
+/// "中文" in GBK
+const GBK: &[u8] = b"\xD6\xD0\xCE\xC4";
+
+/// Payload of a response, without the single packet header
+fn payload(datagram: &[u8]) -> Cursor<Vec<u8>> {
+    Cursor::new(datagram[4..].to_vec())
+}
+
+#[test]
+fn test_bytes_kept() {
+    let mut info = common::info();
+    info.name = Text::from(CP1251);
+    info.map = Text::from(GBK);
+
+    let parsed = Info::from_cursor(payload(&info.to_bytes())).unwrap();
+    assert_eq!(parsed.name.as_bytes(), CP1251);
+    assert_eq!(parsed.map.as_bytes(), GBK);
+    assert_eq!(parsed.game, "Counter-Strike: Global Offensive");
+
+    let mut players = common::players();
+    players[1].name = Text::from(CP1251);
+    let parsed = Player::from_cursor(payload(&Player::vec_to_bytes(players)), 0).unwrap();
+    assert_eq!(parsed[0].name, "alice");
+    assert_eq!(parsed[1].name.as_bytes(), CP1251);
+
+    let rules = vec![Rule {
+        name: "hostname".into(),
+        value: Text::from(GBK),
+    }];
+    let parsed = Rule::from_cursor(payload(&Rule::vec_to_bytes(rules.clone()))).unwrap();
+    assert_eq!(parsed, rules);
+}
+
+#[test]
+fn test_lossy() {
+    let text = Text::from(CP1251);
+    assert_eq!(text.to_str(), None);
+    assert_eq!(text.to_string_lossy(), "\u{FFFD}".repeat(4));
+    assert_eq!(text.to_string(), "\u{FFFD}".repeat(4));
+    assert_eq!(format!("{:?}", text), "Text([192, 240, 236, 224])");
+
+    let text = Text::from("de_dust2");
+    assert_eq!(text.to_str(), Some("de_dust2"));
+    assert_eq!(text, "de_dust2");
+    assert_eq!(format!("{:?}", text), "\"de_dust2\"");
+}
+
+#[cfg(feature = "encoding")]
+#[test]
+fn test_decode() {
+    use crowbar_a2s::encoding_rs::{GBK as GBK_ENCODING, WINDOWS_1251};
+
+    assert_eq!(Text::from(CP1251).decode(WINDOWS_1251), "Арма");
+    assert_eq!(Text::from(GBK).decode(GBK_ENCODING), "中文");
+
+    // UTF-8 is left as is, whatever the legacy encoding
+    assert_eq!(Text::from("Арма").decode(WINDOWS_1251), "Арма");
+}
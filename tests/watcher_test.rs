@@ -0,0 +1,167 @@
+#![cfg(feature = "async")]
+
+mod common;
+
+use crowbar_a2s::client_async::watcher::{Change, Event};
+use crowbar_a2s::server::{A2SServerAsync, ServerState};
+use crowbar_a2s::types::{Info, Player, Rule};
+use crowbar_a2s::Watcher;
+use futures::StreamExt;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+type State = (Info, Vec<Player>, Vec<Rule>);
+
+/// Server state the test changes between polls
+#[derive(Clone)]
+struct Shared(Arc<Mutex<State>>);
+
+impl ServerState for Shared {
+    fn info(&self) -> Info {
+        self.0.lock().unwrap().0.clone()
+    }
+
+    fn players(&self) -> Vec<Player> {
+        self.0.lock().unwrap().1.clone()
+    }
+
+    fn rules(&self) -> Vec<Rule> {
+        self.0.lock().unwrap().2.clone()
+    }
+}
+
+async fn spawn_server() -> (SocketAddr, Shared, tokio::task::JoinHandle<()>) {
+    let state = Shared(Arc::new(Mutex::new((
+        common::info(),
+        common::players(),
+        common::rules(),
+    ))));
+    let server = A2SServerAsync::bind("127.0.0.1:0", state.clone())
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+    let task = tokio::spawn(async move {
+        server.run().await.ok();
+    });
+    (addr, state, task)
+}
+
+fn watcher() -> Watcher {
+    let client = crowbar_a2s::Builder::new()
+        .timeout(Duration::from_millis(200))
+        .build_async()
+        .unwrap();
+    Watcher::new(client)
+}
+
+fn changes(events: Vec<Event>) -> Vec<Change> {
+    events.into_iter().map(|event| event.change).collect()
+}
+
+#[tokio::test]
+async fn test_watcher_changes() {
+    let (addr, state, task) = spawn_server().await;
+    let silent = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let silent = silent.local_addr().unwrap();
+
+    let mut watcher = watcher();
+    watcher.server(addr).server(silent).player_threshold(3);
+
+    let events = watcher.poll().await;
+    assert_eq!(
+        events,
+        vec![
+            Event {
+                server: addr,
+                change: Change::Online
+            },
+            Event {
+                server: silent,
+                change: Change::Offline
+            },
+        ]
+    );
+    assert_eq!(watcher.snapshot(addr).unwrap().info.map, "de_dust2");
+    assert!(watcher.poll().await.is_empty());
+
+    {
+        let mut state = state.0.lock().unwrap();
+        state.0.map = "de_inferno".into();
+        state.0.version = "1.38.8.0".into();
+        state.0.players = 3;
+        // bob reconnected, carol joined
+        state.1[1].duration = 5.0;
+        state.1.push(Player {
+            index: 0,
+            name: "carol".into(),
+            score: 0,
+            duration: 1.0,
+            the_ship: None,
+        });
+        state.2[1].value = "600".into();
+        state.2.remove(0);
+    }
+
+    let polled = changes(watcher.poll().await);
+    let expected = [
+        Change::MapChanged {
+            from: "de_dust2".into(),
+            to: "de_inferno".into(),
+        },
+        Change::VersionChanged {
+            from: "1.38.7.9".into(),
+            to: "1.38.8.0".into(),
+        },
+        Change::PlayerLeft {
+            name: "bob".into(),
+            duration: 42.0,
+        },
+        Change::PlayerJoined { name: "bob".into() },
+        Change::PlayerJoined {
+            name: "carol".into(),
+        },
+        Change::PlayerThreshold {
+            threshold: 3,
+            players: 3,
+            rising: true,
+        },
+        Change::RuleChanged {
+            name: "mp_friendlyfire".into(),
+            from: Some("0".into()),
+            to: None,
+        },
+        Change::RuleChanged {
+            name: "sv_gravity".into(),
+            from: Some("800".into()),
+            to: Some("600".into()),
+        },
+    ];
+    assert_eq!(polled, expected);
+
+    task.abort();
+    let _ = task.await;
+    assert_eq!(changes(watcher.poll().await), vec![Change::Offline]);
+    assert!(watcher.snapshot(addr).is_none());
+}
+
+#[tokio::test]
+async fn test_watcher_stream() {
+    let (addr, state, _task) = spawn_server().await;
+
+    let mut watcher = watcher();
+    watcher.interval(Duration::from_millis(50)).server(addr);
+    let mut events = std::pin::pin!(watcher.watch());
+
+    assert_eq!(events.next().await.unwrap().change, Change::Online);
+
+    state.0.lock().unwrap().0.map = "cs_office".into();
+    let event = events.next().await.unwrap();
+    assert_eq!(
+        event.change,
+        Change::MapChanged {
+            from: "de_dust2".into(),
+            to: "cs_office".into(),
+        }
+    );
+}